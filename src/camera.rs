@@ -0,0 +1,397 @@
+use avian2d::prelude::*;
+use bevy::{prelude::*, transform::TransformSystem};
+
+// Below this horizontal speed the target counts as stopped and the look-ahead recenters
+const LOOK_AHEAD_MIN_SPEED: f32 = 1.0;
+// Spring steps up to this fraction of the spring's time scale are stable and never overshoot
+const SPRING_STEP_FRACTION: f32 = 0.5;
+
+pub struct FollowCameraPlugin;
+
+impl Plugin for FollowCameraPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            PostUpdate,
+            // Following before propagation keeps the camera's `GlobalTransform` up to date
+            (spring_follow, follow_camera).before(TransformSystem::TransformPropagate),
+        );
+    }
+}
+
+/// Makes a camera ease towards `target` with exponential smoothing.
+///
+/// Higher `smoothing` values catch up faster. The result doesn't depend on the frame rate.
+#[derive(Component)]
+pub struct FollowCamera {
+    pub target: Entity,
+    pub smoothing: f32,
+}
+
+/// A rectangle around a [`FollowCamera`]'s center that the target can move within without
+/// scrolling the view.
+#[derive(Component)]
+pub struct DeadZone {
+    pub half_extents: Vec2,
+}
+
+impl DeadZone {
+    /// The closest camera position to `camera` that keeps `target` inside the dead zone.
+    pub fn clamp_camera(&self, camera: Vec2, target: Vec2) -> Vec2 {
+        let offset = target - camera;
+        camera + offset - offset.clamp(-self.half_extents, self.half_extents)
+    }
+}
+
+/// Shifts a [`FollowCamera`]'s target this far in the direction the target is moving, so more
+/// of what's ahead is visible.
+///
+/// The shift goes through the camera's smoothing, so it eases in and back out when the target
+/// stops.
+#[derive(Component)]
+pub struct LookAhead(pub f32);
+
+impl LookAhead {
+    /// The offset from the target for the given horizontal velocity.
+    pub fn offset(&self, velocity_x: f32) -> Vec2 {
+        if velocity_x.abs() < LOOK_AHEAD_MIN_SPEED {
+            return Vec2::ZERO;
+        }
+
+        Vec2::new(velocity_x.signum() * self.0, 0.0)
+    }
+}
+
+/// The level rectangle a [`FollowCamera`] is kept inside, so the view never shows anything
+/// beyond the level's edges.
+#[derive(Component)]
+pub struct CameraBounds {
+    pub min: Vec2,
+    pub max: Vec2,
+}
+
+impl CameraBounds {
+    /// Clamps a camera `position` so that `view`, the visible area relative to the camera,
+    /// stays within bounds. On axes where the view is larger than the bounds, the camera is
+    /// centered instead.
+    pub fn clamp(&self, position: Vec2, view: Rect) -> Vec2 {
+        let min = self.min - view.min;
+        let max = self.max - view.max;
+        let center = (min + max) / 2.0;
+
+        Vec2::new(
+            if min.x <= max.x {
+                position.x.clamp(min.x, max.x)
+            } else {
+                center.x
+            },
+            if min.y <= max.y {
+                position.y.clamp(min.y, max.y)
+            } else {
+                center.y
+            },
+        )
+    }
+}
+
+/// Moves `current` towards `target`, covering a `1 - e^(-smoothing * delta_secs)` fraction of
+/// the remaining distance.
+pub fn smooth_towards(current: Vec2, target: Vec2, smoothing: f32, delta_secs: f32) -> Vec2 {
+    current.lerp(target, 1.0 - (-smoothing * delta_secs).exp())
+}
+
+/// Makes a camera follow `target` with a damped spring instead of moving rigidly with it.
+///
+/// A spring is velocity-aware, so fast changes in the target's motion settle smoothly instead of
+/// whipping the camera around.
+#[derive(Component)]
+pub struct SpringFollow {
+    pub target: Entity,
+    pub stiffness: f32,
+    pub damping: f32,
+    velocity: Vec2,
+}
+
+impl SpringFollow {
+    pub fn new(target: Entity, stiffness: f32, damping: f32) -> Self {
+        Self {
+            target,
+            stiffness,
+            damping,
+            velocity: Vec2::ZERO,
+        }
+    }
+
+    /// A spring that settles as fast as possible without overshooting the target.
+    pub fn critically_damped(target: Entity, stiffness: f32) -> Self {
+        Self::new(target, stiffness, 2.0 * stiffness.sqrt())
+    }
+}
+
+/// Advances a damped spring by `delta_secs`, returning its new position and velocity.
+///
+/// Long steps, like the frame after a hitch, are split into shorter ones so the spring can't
+/// diverge.
+pub fn step_spring(
+    mut position: Vec2,
+    mut velocity: Vec2,
+    target: Vec2,
+    stiffness: f32,
+    damping: f32,
+    delta_secs: f32,
+) -> (Vec2, Vec2) {
+    let max_step = SPRING_STEP_FRACTION / stiffness.sqrt().max(damping * 0.5);
+    let substeps = (delta_secs / max_step).ceil().max(1.0) as u32;
+    let step = delta_secs / substeps as f32;
+
+    for _ in 0..substeps {
+        let acceleration = (target - position) * stiffness - velocity * damping;
+        // Semi-implicit Euler, which is stable as long as the steps are short enough
+        velocity += acceleration * step;
+        position += velocity * step;
+    }
+
+    (position, velocity)
+}
+
+fn spring_follow(
+    time: Res<Time>,
+    mut cameras: Query<(&mut Transform, &mut SpringFollow)>,
+    targets: Query<&Transform, Without<SpringFollow>>,
+) {
+    let delta_secs = time.delta_secs();
+
+    for (mut camera_transform, mut follow) in &mut cameras {
+        let Ok(target_transform) = targets.get(follow.target) else {
+            continue;
+        };
+
+        let (position, velocity) = step_spring(
+            camera_transform.translation.xy(),
+            follow.velocity,
+            target_transform.translation.xy(),
+            follow.stiffness,
+            follow.damping,
+            delta_secs,
+        );
+
+        follow.velocity = velocity;
+        // The camera keeps its own depth
+        camera_transform.translation = position.extend(camera_transform.translation.z);
+    }
+}
+
+fn follow_camera(
+    time: Res<Time>,
+    mut cameras: Query<(
+        &mut Transform,
+        &FollowCamera,
+        Option<&DeadZone>,
+        Option<&LookAhead>,
+        Option<&CameraBounds>,
+        Option<&Projection>,
+    )>,
+    targets: Query<(&Transform, Option<&LinearVelocity>), Without<FollowCamera>>,
+) {
+    let delta_secs = time.delta_secs();
+
+    for (mut camera_transform, follow, dead_zone, look_ahead, bounds, projection) in &mut cameras {
+        let Ok((target_transform, target_velocity)) = targets.get(follow.target) else {
+            continue;
+        };
+
+        let camera_position = camera_transform.translation.xy();
+        let mut target_position = target_transform.translation.xy();
+        if let (Some(look_ahead), Some(target_velocity)) = (look_ahead, target_velocity) {
+            target_position += look_ahead.offset(target_velocity.x);
+        }
+
+        let desired_position = match dead_zone {
+            Some(dead_zone) => dead_zone.clamp_camera(camera_position, target_position),
+            None => target_position,
+        };
+
+        let mut position = smooth_towards(
+            camera_position,
+            desired_position,
+            follow.smoothing,
+            delta_secs,
+        );
+
+        // Clamping after smoothing keeps the edges hard rather than letting the camera ease past
+        if let (Some(bounds), Some(Projection::Orthographic(projection))) = (bounds, projection) {
+            position = bounds.clamp(position, projection.area);
+        }
+
+        camera_transform.translation = position.extend(camera_transform.translation.z);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use bevy::time::TimeUpdateStrategy;
+
+    use super::*;
+
+    const STIFFNESS: f32 = 50.0;
+
+    /// A headless app that runs the camera systems at 60 frames per second.
+    fn test_app() -> App {
+        let mut app = App::new();
+        app.add_plugins((MinimalPlugins, TransformPlugin, FollowCameraPlugin))
+            .insert_resource(TimeUpdateStrategy::ManualDuration(Duration::from_secs_f64(
+                1.0 / 60.0,
+            )));
+
+        // The first update only starts the clock
+        app.update();
+        app
+    }
+
+    fn step(app: &mut App, frames: usize) {
+        for _ in 0..frames {
+            app.update();
+        }
+    }
+
+    fn spawn_target(app: &mut App, position: Vec2) -> Entity {
+        app.world_mut()
+            .spawn(Transform::from_translation(position.extend(0.0)))
+            .id()
+    }
+
+    fn position(app: &App, entity: Entity) -> Vec2 {
+        app.world()
+            .get::<Transform>(entity)
+            .unwrap()
+            .translation
+            .xy()
+    }
+
+    #[test]
+    fn critically_damped_spring_settles_on_a_moving_target_without_overshooting() {
+        let damping = 2.0 * STIFFNESS.sqrt();
+        let (mut position, mut velocity) = (Vec2::ZERO, Vec2::ZERO);
+
+        // The target moves right for a second, then stops at x = 100
+        for frame in 0..300 {
+            let target = Vec2::new((frame as f32 + 1.0).min(60.0) / 60.0 * 100.0, 0.0);
+            (position, velocity) =
+                step_spring(position, velocity, target, STIFFNESS, damping, 1.0 / 60.0);
+            assert!(position.x <= 100.0, "overshot to {}", position.x);
+        }
+
+        assert!((position.x - 100.0).abs() < 0.01);
+    }
+
+    #[test]
+    fn spring_stays_stable_over_a_long_frame() {
+        let damping = 2.0 * STIFFNESS.sqrt();
+        let target = Vec2::new(100.0, 0.0);
+
+        let (position, velocity) =
+            step_spring(Vec2::ZERO, Vec2::ZERO, target, STIFFNESS, damping, 1.0);
+
+        assert!(position.is_finite() && velocity.is_finite());
+        assert!(position.x > 0.0 && position.x <= 100.0);
+    }
+
+    #[test]
+    fn follow_camera_eases_towards_its_target() {
+        let mut app = test_app();
+        let target = spawn_target(&mut app, Vec2::new(100.0, 0.0));
+        let camera = app
+            .world_mut()
+            .spawn((
+                Transform::default(),
+                FollowCamera {
+                    target,
+                    smoothing: 5.0,
+                },
+            ))
+            .id();
+
+        app.update();
+        let first_step = position(&app, camera).x;
+        assert!(first_step > 0.0 && first_step < 100.0);
+
+        step(&mut app, 120);
+        assert!((position(&app, camera).x - 100.0).abs() < 0.01);
+    }
+
+    #[test]
+    fn dead_zone_holds_the_camera_until_the_target_leaves_it() {
+        let dead_zone = DeadZone {
+            half_extents: Vec2::new(50.0, 30.0),
+        };
+        let camera = Vec2::new(10.0, 10.0);
+
+        assert_eq!(
+            dead_zone.clamp_camera(camera, Vec2::new(40.0, -15.0)),
+            camera
+        );
+        // 20 past the right edge of the dead zone
+        assert_eq!(
+            dead_zone.clamp_camera(camera, Vec2::new(80.0, 10.0)),
+            Vec2::new(30.0, 10.0)
+        );
+    }
+
+    #[test]
+    fn look_ahead_leads_a_moving_target_and_recenters_when_it_stops() {
+        let mut app = test_app();
+        let target = app
+            .world_mut()
+            .spawn((Transform::default(), LinearVelocity(Vec2::X * 100.0)))
+            .id();
+        let camera = app
+            .world_mut()
+            .spawn((
+                Transform::default(),
+                FollowCamera {
+                    target,
+                    smoothing: 10.0,
+                },
+                LookAhead(50.0),
+            ))
+            .id();
+
+        step(&mut app, 120);
+        assert!((position(&app, camera) - Vec2::new(50.0, 0.0)).length() < 0.01);
+
+        app.world_mut().get_mut::<LinearVelocity>(target).unwrap().0 = Vec2::ZERO;
+        step(&mut app, 120);
+        assert!(position(&app, camera).length() < 0.01);
+    }
+
+    #[test]
+    fn bounds_keep_the_view_inside_the_level_near_a_corner() {
+        let bounds = CameraBounds {
+            min: Vec2::ZERO,
+            max: Vec2::new(1000.0, 600.0),
+        };
+        let view = Rect::new(-200.0, -150.0, 200.0, 150.0);
+
+        // Following a target standing in the bottom left corner
+        let position = bounds.clamp(Vec2::new(50.0, 40.0), view);
+
+        assert_eq!(position, Vec2::new(200.0, 150.0));
+        assert!((position + view.min).cmpge(bounds.min).all());
+        assert!((position + view.max).cmple(bounds.max).all());
+    }
+
+    #[test]
+    fn bounds_center_a_view_larger_than_the_level() {
+        let bounds = CameraBounds {
+            min: Vec2::ZERO,
+            max: Vec2::new(300.0, 600.0),
+        };
+        let view = Rect::new(-200.0, -150.0, 200.0, 150.0);
+
+        assert_eq!(
+            bounds.clamp(Vec2::new(50.0, 40.0), view),
+            Vec2::new(150.0, 150.0)
+        );
+    }
+}
@@ -0,0 +1,4283 @@
+use std::{collections::HashMap, error::Error, fmt};
+
+use avian2d::{math::*, parry::shape::ShapeType, prelude::*};
+use bevy::{color::palettes::css, ecs::entity::EntityHashSet, prelude::*};
+
+const MAX_BOUNCES: usize = 4;
+const SKIN_WIDTH: Scalar = 0.5;
+// Leftover slide motion shorter than this isn't worth another cast
+const MIN_SLIDE_DISTANCE: Scalar = 1e-3;
+// Steps that would move the controller less than this don't move it at all
+const MIN_MOVEMENT_DISTANCE: Scalar = 0.01;
+const SPRINT_MULTIPLIER: Scalar = 1.75;
+// Damping factors are given per frame at this rate and rescaled to the actual frame time
+const DAMPING_REFERENCE_RATE: Scalar = 60.0;
+// How far sideways we look for a wall when deciding whether the controller is wall sliding
+const WALL_CHECK_DISTANCE: Scalar = 2.0;
+// The default vertical speed below which a controller counts as hanging at its apex
+const APEX_SPEED_THRESHOLD: Scalar = 40.0;
+// The steepest angle between the velocity and a wall at which a `WallBounce` still triggers
+const WALL_BOUNCE_MAX_ANGLE: Scalar = PI / 6.0;
+// How many ground hits, and so normals, the ground caster collects by default
+const NORMAL_COUNT: u32 = 10;
+// How long a one-way platform is ignored after dropping through it, in seconds
+const DROP_THROUGH_DURATION: f32 = 0.25;
+// How much `SquashStretch` lengthens a controller moving at its terminal velocity
+const MAX_STRETCH: Scalar = 0.3;
+// How much `SquashStretch` flattens a controller on the frame it lands
+const LANDING_SQUASH: Scalar = 0.3;
+// How far past the controller's side the ledge casts look for a ledge
+const LEDGE_REACH: Scalar = 4.0;
+// How far below the top of the controller a wall has to be found for it to count as a ledge
+const LEDGE_PROBE_DEPTH: Scalar = 8.0;
+// How many nudge distances, evenly spaced up to the `CornerCorrectionWidth`, are tried
+const CORNER_CORRECTION_STEPS: usize = 4;
+// Debug velocity arrows are drawn this many seconds of travel long
+const DEBUG_VELOCITY_SCALE: Scalar = 0.2;
+// The length of debug ground normal arrows
+const DEBUG_NORMAL_LENGTH: Scalar = 20.0;
+// How far above a controller's center its debug text is shown
+const DEBUG_TEXT_OFFSET: Scalar = 50.0;
+
+/// Adds the character controller systems.
+///
+/// Only avian's `PhysicsPlugins` are required alongside it, so the controller also runs in a
+/// headless `App` built from `MinimalPlugins`. Keyboard input is skipped when there is no
+/// `ButtonInput<KeyCode>`, in which case controllers are driven by sending [`ControllerAction`]s.
+pub struct CharacterControllerPlugin {
+    /// Movement tuning for controllers that aren't given their own with
+    /// [`CharacterControllerBundle::with_movement`].
+    pub default_tuning: ControllerTuning,
+    /// Logs every controller state transition and inserts [`ControllerDebug`].
+    pub debug: bool,
+    /// Overrides the fixed timestep rate when set.
+    pub physics_rate: Option<PhysicsRate>,
+}
+
+impl CharacterControllerPlugin {
+    /// A plugin that runs the controller, and the rest of the fixed timestep, at `hz`.
+    pub fn new(hz: f64) -> Self {
+        Self {
+            physics_rate: Some(PhysicsRate(hz)),
+            ..default()
+        }
+    }
+}
+
+impl Default for CharacterControllerPlugin {
+    fn default() -> Self {
+        Self {
+            default_tuning: ControllerTuning::default(),
+            debug: false,
+            physics_rate: None,
+        }
+    }
+}
+
+/// The rate, in hertz, of the fixed timestep that the controller and physics run at.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PhysicsRate(pub f64);
+
+impl Plugin for CharacterControllerPlugin {
+    fn build(&self, app: &mut App) {
+        if let Some(PhysicsRate(hz)) = self.physics_rate {
+            app.insert_resource(Time::<Fixed>::from_hz(hz));
+        }
+
+        if self.debug {
+            app.init_resource::<ControllerDebug>()
+                .add_systems(
+                    FixedUpdate,
+                    log_state_transitions.after(update_controller_state),
+                )
+                .add_systems(
+                    Update,
+                    (spawn_debug_text, update_debug_text)
+                        .chain()
+                        .run_if(resource_exists::<ControllerDebug>),
+                );
+        }
+
+        app.add_event::<ControllerAction>()
+            .add_event::<TriggerEnter>()
+            .add_event::<TriggerExit>()
+            .add_event::<RespawnEvent>()
+            .add_event::<ResetControllersEvent>()
+            .add_event::<ChangeColliderEvent>()
+            .add_event::<ApplyImpulse>()
+            .add_event::<StateChanged>()
+            .add_event::<LandEvent>()
+            .insert_resource(self.default_tuning)
+            .init_resource::<InputBindings>()
+            .init_resource::<HeldInput>()
+            .init_resource::<InputQueue>()
+            .init_resource::<CollideAndSlideSettings>()
+            .add_systems(PreUpdate, apply_default_tuning)
+            // Input is sampled every frame so no presses are missed, everything that moves the
+            // controller runs on the same fixed timestep as the physics
+            .add_systems(
+                Update,
+                (
+                    keyboard_input
+                        .run_if(resource_exists::<ButtonInput<KeyCode>>)
+                        .in_set(ControllerSet::Input),
+                    debug_draw_controller.run_if(
+                        resource_exists::<ControllerDebug>.and(resource_exists::<GizmoConfigStore>),
+                    ),
+                ),
+            )
+            .configure_sets(
+                FixedUpdate,
+                (
+                    ControllerSet::Input,
+                    ControllerSet::Gravity,
+                    ControllerSet::Movement,
+                    ControllerSet::ModifyVelocity,
+                )
+                    .chain(),
+            )
+            .add_systems(
+                FixedUpdate,
+                (
+                    emit_input.in_set(ControllerSet::Input),
+                    (
+                        freeze,
+                        thaw,
+                        respawn,
+                        reset_controllers,
+                        change_collider,
+                        flip_gravity,
+                        (tick_drop_through, drop_through).chain(),
+                    )
+                        .before(ControllerSet::Gravity),
+                    (
+                        update_grounded,
+                        (apply_gravity, update_air_time, detect_landing),
+                    )
+                        .chain()
+                        .in_set(ControllerSet::Gravity),
+                    (
+                        tick_dash,
+                        tick_jump_timers,
+                        movement,
+                        cut_jump,
+                        jump_thrust,
+                        ledge_hang,
+                        apply_damping,
+                        update_controller_state,
+                        update_jump_state,
+                    )
+                        .chain()
+                        .in_set(ControllerSet::Movement),
+                    update_facing.in_set(ControllerSet::Movement),
+                    apply_impulses
+                        .after(ControllerSet::Movement)
+                        .before(ControllerSet::ModifyVelocity),
+                    // Clamped last so nothing that adds velocity can push past the limit
+                    clamp_horizontal_speed.after(ControllerSet::ModifyVelocity),
+                    detect_triggers.after(ControllerSet::ModifyVelocity),
+                    update_squash_stretch.after(ControllerSet::ModifyVelocity),
+                ),
+            )
+            .add_systems(
+                PhysicsSchedule,
+                // Velocities are corrected after collision detection but before the solver
+                // integrates them, so the controller never moves into geometry. Overlaps are
+                // resolved first so the slide starts from a valid position.
+                (
+                    overlap_recovery,
+                    push_controllers.run_if(resource_exists::<ControllerPushConfig>),
+                    kinematic_collision_response,
+                    clear_skip_collision,
+                    update_speed,
+                )
+                    .chain()
+                    .in_set(NarrowPhaseSet::Last)
+                    .in_set(ControllerSet::Collision),
+            );
+    }
+}
+
+/// The phases of the controller, for ordering user systems relative to them.
+///
+/// `Input`, `Gravity`, `Movement` and `ModifyVelocity` run in that order in `FixedUpdate`, while
+/// `Collision` runs in avian's `PhysicsSchedule`. Keyboard sampling also happens in `Input`, but
+/// in `Update`.
+#[derive(SystemSet, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ControllerSet {
+    Input,
+    Movement,
+    Gravity,
+    /// Empty by default. Systems added here see the velocity the controller intends to move
+    /// with this step and can change it before any collisions are resolved, which makes it the
+    /// place for wind, currents or custom abilities.
+    ModifyVelocity,
+    Collision,
+}
+
+/// A movement input action, sent to controllers in a [`ControllerAction`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum MovementAction {
+    /// Walks towards the controller's right, perpendicular to its up, for a positive direction.
+    /// An upside-down controller's right is the other way on screen.
+    Walk(Scalar),
+    Sprint(Scalar),
+    Jump,
+    Dash(Scalar),
+    /// Sent while the down key is held. The controller doesn't react to it itself, it's a hook
+    /// for gameplay code.
+    Crouch,
+    /// Sent when the player asks to fall through the platform they're standing on.
+    DropThrough,
+    /// Sent while the down key is held. Airborne controllers fall faster by their
+    /// [`FastFallMultiplier`].
+    FastFall,
+    /// Reverses the controller's [`Gravity`] and [`UpDirection`], so the ceiling becomes the
+    /// floor. The controller keeps its velocity, so it eases into the new direction instead of
+    /// snapping.
+    FlipGravity,
+    /// Sent while the up key is held. Climbs a controller hanging from a ledge onto it, see
+    /// [`LedgeGrab`].
+    Climb,
+    /// Sent while the jump key is held. Controllers with a [`MinJumpHeight`] cut their jump
+    /// short once it stops.
+    JumpHeld,
+}
+
+/// An event that applies `action` to the controllers it targets.
+///
+/// This is the only way controllers receive input. The keyboard input sends it for players,
+/// but any system can send it, so AI or scripted controllers work the same way as player ones
+/// and don't need a keyboard at all. Actions are applied on the next fixed step. Held actions
+/// like [`MovementAction::Walk`] have to be sent on every step they should last.
+///
+/// Controllers are player 0 by default, so ones driven only by other systems should be moved to
+/// a player without keyboard bindings with [`CharacterControllerBundle::with_player`].
+#[derive(Event, Clone, Copy, Debug, PartialEq)]
+pub struct ControllerAction {
+    pub target: ActionTarget,
+    pub action: MovementAction,
+}
+
+/// Which controllers a [`ControllerAction`] applies to.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ActionTarget {
+    /// Every controller with this [`PlayerIndex`].
+    Player(u8),
+    /// A single controller.
+    Controller(Entity),
+}
+
+impl ControllerAction {
+    /// An action for every controller of `player`.
+    pub fn player(player: u8, action: MovementAction) -> Self {
+        Self {
+            target: ActionTarget::Player(player),
+            action,
+        }
+    }
+
+    /// An action for the controller `entity` only.
+    pub fn controller(entity: Entity, action: MovementAction) -> Self {
+        Self {
+            target: ActionTarget::Controller(entity),
+            action,
+        }
+    }
+
+    /// Whether the action is meant for the controller `entity` of `player`.
+    pub fn targets(&self, entity: Entity, player: &PlayerIndex) -> bool {
+        match self.target {
+            ActionTarget::Player(target) => target == player.0,
+            ActionTarget::Controller(target) => target == entity,
+        }
+    }
+}
+
+/// Which player's input drives a controller, for local multiplayer. Defaults to player 0.
+///
+/// A controller can have its own [`InputBindings`] component, which its player's keyboard input
+/// uses instead of the [`InputBindings`] resource.
+#[derive(Component, Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct PlayerIndex(pub u8);
+
+/// Sent when a controller starts overlapping a [`Sensor`] collider.
+#[derive(Event, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TriggerEnter {
+    pub controller: Entity,
+    pub trigger: Entity,
+}
+
+/// Sent when a controller stops overlapping a [`Sensor`] collider.
+#[derive(Event, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TriggerExit {
+    pub controller: Entity,
+    pub trigger: Entity,
+}
+
+/// Teleports a controller to `position` and clears its motion, e.g. when returning to a
+/// checkpoint.
+#[derive(Event, Clone, Copy, Debug)]
+pub struct RespawnEvent {
+    pub entity: Entity,
+    pub position: Vector,
+}
+
+/// Clears every controller's transient state, i.e. its velocity, state, jump timers, used air
+/// jumps, jump thrust fuel, dash cooldown, ledge hang and drop-through, e.g. when loading a new
+/// level.
+///
+/// Unlike [`RespawnEvent`], controllers stay where they are.
+#[derive(Event, Clone, Copy, Debug, Default)]
+pub struct ResetControllersEvent;
+
+/// Swaps a controller's collider for `collider`, e.g. to shrink it while crouching.
+///
+/// The ground caster is resized to match and the controller is moved so its feet stay where they
+/// were. A larger collider that no longer fits is pushed out of the geometry it overlaps.
+#[derive(Event, Clone, Debug)]
+pub struct ChangeColliderEvent {
+    pub entity: Entity,
+    pub collider: Collider,
+}
+
+/// Adds `impulse` to a controller's velocity, for knockback, launch pads and similar pushes.
+///
+/// The result is still limited by the controller's [`TerminalVelocity`] and
+/// [`MaxHorizontalSpeed`].
+#[derive(Event, Clone, Copy, Debug)]
+pub struct ApplyImpulse {
+    pub entity: Entity,
+    pub impulse: Vector,
+}
+
+/// Sent when a controller's [`CharacterControllerState`] changes, for one-shot animations and
+/// sounds.
+#[derive(Event, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct StateChanged {
+    pub entity: Entity,
+    pub from: CharacterControllerState,
+    pub to: CharacterControllerState,
+}
+
+/// Sent when a controller lands, with how far it fell from the highest point it reached in the
+/// air.
+#[derive(Event, Clone, Copy, Debug, PartialEq)]
+pub struct LandEvent {
+    pub entity: Entity,
+    pub fall_distance: Scalar,
+}
+
+/// The keys read by the controller's keyboard input.
+///
+/// As a resource, these are player 0's keys. As a component on a controller, they're the keys of
+/// that controller's [`PlayerIndex`].
+#[derive(Resource, Component, Clone, Debug)]
+pub struct InputBindings {
+    pub left: Vec<KeyCode>,
+    pub right: Vec<KeyCode>,
+    pub up: Vec<KeyCode>,
+    pub down: Vec<KeyCode>,
+    pub jump: Vec<KeyCode>,
+    pub sprint: Vec<KeyCode>,
+    pub dash: Vec<KeyCode>,
+    pub flip_gravity: Vec<KeyCode>,
+    pub drop_through: DropThroughTrigger,
+}
+
+impl Default for InputBindings {
+    fn default() -> Self {
+        Self {
+            left: vec![KeyCode::KeyA, KeyCode::ArrowLeft],
+            right: vec![KeyCode::KeyD, KeyCode::ArrowRight],
+            up: vec![KeyCode::KeyW, KeyCode::ArrowUp],
+            down: vec![KeyCode::KeyS, KeyCode::ArrowDown],
+            jump: vec![KeyCode::Space],
+            sprint: vec![KeyCode::ShiftLeft, KeyCode::ShiftRight],
+            dash: vec![KeyCode::KeyK],
+            flip_gravity: vec![KeyCode::KeyG],
+            drop_through: DropThroughTrigger::DownAndJump,
+        }
+    }
+}
+
+/// Which key combination sends [`MovementAction::DropThrough`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DropThroughTrigger {
+    /// Holding down drops through, so crouching and dropping through are the same input.
+    HoldDown,
+    /// Pressing jump while holding down drops through instead of jumping, leaving down alone
+    /// free for crouching.
+    DownAndJump,
+}
+
+/// A marker component indicating that an entity is using a character controller.
+#[derive(Component)]
+pub struct CharacterController;
+
+/// A marker component indicating that an entity is on the ground.
+///
+/// The controller inserts and removes it itself, so it's meant to be queried with
+/// `With<Grounded>` or `Has<Grounded>` rather than inserted by hand.
+#[derive(Component)]
+#[component(storage = "SparseSet")]
+pub struct Grounded;
+
+/// The normal of the ground a controller is standing on, or `None` while it's airborne.
+///
+/// When several surfaces are touched at once, like in a concave corner, the flattest one is used.
+#[derive(Component, Clone, Copy, Debug, Default, PartialEq)]
+pub struct GroundNormal(pub Option<Dir2>);
+
+/// Pauses a controller in place, e.g. for pause menus or dialog.
+///
+/// A frozen controller ignores input, gravity, impulses and collisions. Its velocity is stored
+/// and [`LinearVelocity`] reads zero until the marker is removed, at which point the controller
+/// carries on exactly as it was moving before.
+#[derive(Component, Clone, Copy, Debug)]
+#[component(storage = "SparseSet")]
+pub struct Frozen;
+
+/// Skips the controller's collision response for the next physics step, so a controller placed
+/// right against geometry isn't pushed out of it before it has settled. The controller stands
+/// still for that step, and the marker is removed automatically.
+///
+/// See [`teleport`].
+#[derive(Component, Clone, Copy, Debug)]
+#[component(storage = "SparseSet")]
+pub struct SkipCollision;
+
+/// The velocity a [`Frozen`] controller had when it was frozen.
+#[derive(Component)]
+struct FrozenVelocity(Vector);
+
+/// How much the ground caster's shape is scaled relative to the controller's collider.
+///
+/// Slightly less than 1.0 keeps the sides of the caster from touching walls, so they aren't
+/// detected as ground. Set it with [`CharacterControllerBundle::with_ground_caster_scale`].
+#[derive(Component, Clone, Copy, Debug, PartialEq)]
+pub struct GroundCasterScale(pub Scalar);
+
+impl Default for GroundCasterScale {
+    fn default() -> Self {
+        Self(0.99)
+    }
+}
+
+/// Keeps a controller [`Grounded`] for this many physics frames after the ground cast last found
+/// ground, so a single missed hit on rough terrain doesn't make it flicker.
+#[derive(Component, Clone, Copy, Debug)]
+pub struct GroundedGrace(pub u8);
+
+/// How many physics frames in a row the ground cast hasn't found ground.
+#[derive(Component, Default)]
+struct UngroundedFrames(u8);
+
+/// Seconds since the controller was last [`Grounded`], or zero while it's on the ground.
+#[derive(Component, Clone, Copy, Debug, Default, PartialEq)]
+pub struct AirTime(pub f32);
+
+/// Landings from a fall longer than this count as a [`HardLanding`].
+#[derive(Component, Clone, Copy, Debug)]
+pub struct HardLandingThreshold(pub Scalar);
+
+/// Added for a single physics frame when a controller lands from a fall longer than its
+/// [`HardLandingThreshold`], for fall damage or stumble animations.
+#[derive(Component, Clone, Copy, Debug)]
+#[component(storage = "SparseSet")]
+pub struct HardLanding;
+
+/// The highest point along up the controller has reached since it left the ground, or `None`
+/// while it's grounded.
+#[derive(Component, Default)]
+struct FallApex(Option<Scalar>);
+
+/// A scale for the controller's sprite or mesh, with `y` along up: stretched while it moves fast
+/// vertically and squashed on the frame it lands. The area stays the same.
+///
+/// The controller only computes it, applying it is up to the renderer.
+#[derive(Component, Clone, Copy, Debug, PartialEq)]
+pub struct SquashStretch {
+    pub scale: Vec2,
+}
+
+impl Default for SquashStretch {
+    fn default() -> Self {
+        Self { scale: Vec2::ONE }
+    }
+}
+
+/// How fast the controller moves after its latest collision response, for HUDs and similar.
+#[derive(Component, Clone, Copy, Debug, Default, PartialEq)]
+pub struct Speed {
+    /// The speed perpendicular to the controller's up direction.
+    pub horizontal: Scalar,
+    pub total: Scalar,
+    /// The direction of motion, or `None` while the controller is still.
+    pub direction: Option<Dir2>,
+}
+
+/// The motion state of a character controller, meant for driving animation state machines.
+#[derive(Component, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum CharacterControllerState {
+    #[default]
+    Idle,
+    Walking,
+    Sprinting,
+    Jumping,
+    Falling,
+    WallSliding,
+    /// Hanging from a ledge, see [`LedgeGrab`].
+    LedgeHang,
+}
+
+/// The direction a controller faces, meant for flipping sprites.
+///
+/// Follows the latest walk or sprint input and keeps its direction when the input stops.
+#[derive(Component, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Facing {
+    Left,
+    #[default]
+    Right,
+}
+
+/// The acceleration used for character movement.
+#[derive(Component)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MovementAcceleration(pub Scalar);
+
+/// The acceleration used for character movement while airborne.
+#[derive(Component)]
+pub struct AirAcceleration(pub Scalar);
+
+impl Default for AirAcceleration {
+    fn default() -> Self {
+        Self(800.0)
+    }
+}
+
+/// The damping factor used for slowing down horizontal movement on the ground.
+#[derive(Component)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MovementDamping(pub Scalar);
+
+/// The damping factor used for slowing down horizontal movement while airborne.
+#[derive(Component)]
+pub struct AirDamping(pub Scalar);
+
+impl Default for AirDamping {
+    fn default() -> Self {
+        Self(0.98)
+    }
+}
+
+/// The initial vertical speed of a jump.
+#[derive(Component)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct JumpImpulse(pub Scalar);
+
+/// The gravitational acceleration applied to a character controller.
+#[derive(Component)]
+pub struct Gravity(pub Vector);
+
+impl Gravity {
+    /// The direction opposite to gravity, which is what the controller treats as "up".
+    pub fn up(&self) -> Dir2 {
+        Dir2::new(-self.0).unwrap_or(Dir2::Y)
+    }
+}
+
+/// Whether gravity is applied to the controller, so it can be switched off for flying or
+/// scripted sequences without removing its [`Gravity`].
+#[derive(Component, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct GravityEnabled(pub bool);
+
+impl Default for GravityEnabled {
+    fn default() -> Self {
+        Self(true)
+    }
+}
+
+/// Scales gravity while the controller is moving down, so it falls faster than it rises.
+#[derive(Component)]
+pub struct FallGravityMultiplier(pub Scalar);
+
+impl Default for FallGravityMultiplier {
+    fn default() -> Self {
+        Self(1.8)
+    }
+}
+
+/// Scales gravity while an airborne controller receives [`MovementAction::FastFall`].
+#[derive(Component)]
+pub struct FastFallMultiplier(pub Scalar);
+
+impl Default for FastFallMultiplier {
+    fn default() -> Self {
+        Self(2.0)
+    }
+}
+
+/// Overrides which way is up for a controller, which is otherwise the direction opposite to its
+/// [`Gravity`]. Ground, slope and wall checks are all made relative to it, so it allows
+/// wall-walking sections where "up" doesn't match gravity.
+#[derive(Component, Clone, Copy, Debug, PartialEq)]
+pub struct UpDirection(pub Dir2);
+
+/// The up direction of a controller, from its [`UpDirection`] if it has one.
+fn controller_up(gravity: &Gravity, up_direction: Option<&UpDirection>) -> Dir2 {
+    up_direction.map_or_else(|| gravity.up(), |up_direction| up_direction.0)
+}
+
+/// The maximum speed the controller can fall at.
+#[derive(Component)]
+pub struct TerminalVelocity(pub Scalar);
+
+impl Default for TerminalVelocity {
+    fn default() -> Self {
+        Self(300.0)
+    }
+}
+
+/// The steepest slope, in radians, that the controller can stand on.
+#[derive(Component)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MaxSlopeAngle(pub Scalar);
+
+/// Shapes how walking and sprinting build up to top speed, the speed at which the controller's
+/// damping takes away as much as its acceleration adds.
+///
+/// Without one, the acceleration is the same at every speed, like [`MovementCurve::Linear`].
+#[derive(Component, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum MovementCurve {
+    /// The same acceleration at every speed.
+    #[default]
+    Linear,
+    /// Slow to get going, picking up as the controller speeds up.
+    EaseIn,
+    /// Quick off the mark, tapering off towards top speed.
+    EaseOut,
+    /// Strongest from a standstill and fading out completely at top speed.
+    Exponential,
+}
+
+impl MovementCurve {
+    /// The multiplier on the acceleration at `progress`, the fraction of top speed the controller
+    /// is already moving at in the walk direction.
+    pub fn acceleration_scale(&self, progress: Scalar) -> Scalar {
+        let progress = progress.clamp(0.0, 1.0);
+
+        match self {
+            MovementCurve::Linear => 1.0,
+            MovementCurve::EaseIn => 0.5 + progress,
+            MovementCurve::EaseOut => 1.5 - progress,
+            MovementCurve::Exponential => 2.0 * (1.0 - progress),
+        }
+    }
+}
+
+/// The speed that walking at `acceleration` levels off at under `damping`.
+fn top_walk_speed(acceleration: Scalar, damping: Scalar) -> Scalar {
+    if damping >= 1.0 {
+        return Scalar::INFINITY;
+    }
+
+    acceleration / DAMPING_REFERENCE_RATE * damping / (1.0 - damping)
+}
+
+/// The `curve`'s multiplier for walking in `direction` at `velocity`, whose progress towards
+/// `top_speed` is measured along the controller's right, perpendicular to `up`.
+///
+/// A top speed of zero or infinity leaves nothing to measure progress against, so the
+/// acceleration is left as it is.
+fn curve_scale(
+    curve: &MovementCurve,
+    velocity: Vector,
+    up: Dir2,
+    direction: Scalar,
+    top_speed: Scalar,
+) -> Scalar {
+    if top_speed <= 0.0 || !top_speed.is_finite() {
+        return 1.0;
+    }
+
+    let right = Vector::new(up.y, -up.x);
+    let walk_speed = velocity.dot(right) * direction.signum();
+    curve.acceleration_scale(walk_speed / top_speed)
+}
+
+/// A hard limit on horizontal speed, applied after every other source of velocity so that
+/// dashes, wall jumps and external impulses can't stack into runaway speed.
+#[derive(Component)]
+pub struct MaxHorizontalSpeed(pub Scalar);
+
+/// Shapes the whole jump arc with a single knob from floaty (0.0) to snappy (1.0).
+///
+/// Under the hood this scales gravity depending on the phase of the jump:
+/// - rising: from 0.8x at 0.0 to 1.4x at 1.0, so snappier jumps reach their peak sooner
+/// - near the apex: from 0.5x at 0.0 to 1.0x at 1.0, so floatier jumps hang in the air
+/// - falling: from 1.0x at 0.0 to 2.5x at 1.0, so snappier jumps come down faster
+///
+/// These multiply with the controller's other gravity multipliers, so a falling controller also
+/// gets its [`FallGravityMultiplier`] on top, 4.5x in total at 1.0 with the default. Set that to
+/// 1.0 to use only this mapping.
+#[derive(Component)]
+pub struct JumpSnappiness(pub Scalar);
+
+impl JumpSnappiness {
+    /// The gravity multiplier for a controller moving at `vertical_speed` along its up direction.
+    pub fn gravity_multiplier(&self, vertical_speed: Scalar) -> Scalar {
+        let snappiness = self.0.clamp(0.0, 1.0);
+        let lerp = |floaty: Scalar, snappy: Scalar| floaty + (snappy - floaty) * snappiness;
+
+        if vertical_speed.abs() < APEX_SPEED_THRESHOLD {
+            lerp(0.5, 1.0)
+        } else if vertical_speed > 0.0 {
+            lerp(0.8, 1.4)
+        } else {
+            lerp(1.0, 2.5)
+        }
+    }
+}
+
+/// Scales gravity while an airborne controller hangs near the apex of its arc, giving jumps a
+/// moment of hang time. Only takes effect together with [`ApexThreshold`].
+#[derive(Component, Clone, Copy)]
+pub struct ApexGravityMultiplier(pub Scalar);
+
+impl Default for ApexGravityMultiplier {
+    fn default() -> Self {
+        Self(0.5)
+    }
+}
+
+/// The vertical speed, along the controller's up direction, below which it counts as being at
+/// its apex for [`ApexGravityMultiplier`].
+#[derive(Component, Clone, Copy)]
+pub struct ApexThreshold(pub Scalar);
+
+impl Default for ApexThreshold {
+    fn default() -> Self {
+        Self(APEX_SPEED_THRESHOLD)
+    }
+}
+
+/// Extra jump speed per unit of horizontal speed, so running jumps go higher than standing ones.
+#[derive(Component, Clone, Copy, Debug, Default)]
+pub struct JumpSpeedBonus(pub Scalar);
+
+/// How far jumps off a slope lean away from the controller's up direction towards the slope's
+/// normal, from 0.0 for straight up to 1.0 for straight along the normal.
+#[derive(Component, Clone, Copy, Debug)]
+pub struct SlopeJump(pub Scalar);
+
+impl SlopeJump {
+    /// Turns the speed a jump gave `velocity` along `up` towards the ground's `normal`.
+    fn redirect(&self, velocity: &mut LinearVelocity, up: Dir2, normal: Dir2) {
+        let jump_speed = velocity.dot(*up);
+        let direction = up.lerp(*normal, self.0.clamp(0.0, 1.0)).normalize_or(*up);
+        velocity.0 += (direction - *up) * jump_speed;
+    }
+}
+
+/// Lets the controller still jump for `duration` seconds after walking off a ledge.
+#[derive(Component, Clone, Copy, Debug, Default)]
+pub struct CoyoteTime {
+    pub duration: f32,
+    remaining: f32,
+}
+
+impl CoyoteTime {
+    pub fn new(duration: f32) -> Self {
+        Self {
+            duration,
+            remaining: 0.0,
+        }
+    }
+
+    /// Seconds left to jump since the controller was last grounded.
+    pub fn remaining(&self) -> f32 {
+        self.remaining
+    }
+}
+
+/// Remembers a jump pressed while airborne for `duration` seconds and performs it on landing,
+/// so presses just before touching the ground aren't lost.
+#[derive(Component, Clone, Copy, Debug, Default)]
+pub struct JumpBuffer {
+    pub duration: f32,
+    remaining: f32,
+}
+
+impl JumpBuffer {
+    pub fn new(duration: f32) -> Self {
+        Self {
+            duration,
+            remaining: 0.0,
+        }
+    }
+
+    /// Seconds left before a buffered jump is dropped, or zero if there isn't one.
+    pub fn remaining(&self) -> f32 {
+        self.remaining
+    }
+}
+
+/// The height a jump always reaches, however briefly jump is held.
+///
+/// Letting go of jump while still rising slows the controller down to the speed that carries it
+/// this much higher, so tapping jump gives short hops. Holding jump is read from
+/// [`MovementAction::JumpHeld`].
+#[derive(Component, Clone, Copy, Debug)]
+pub struct MinJumpHeight(pub Scalar);
+
+/// The height a jump reaches while jump is held, which replaces the controller's
+/// [`JumpImpulse`].
+#[derive(Component, Clone, Copy, Debug)]
+pub struct MaxJumpHeight(pub Scalar);
+
+/// Pushes an airborne controller towards its up direction at `accel` while jump is held, for up
+/// to `fuel` seconds of thrust between landings.
+#[derive(Component, Clone, Copy, Debug)]
+pub struct JumpThrust {
+    pub accel: Scalar,
+    pub fuel: f32,
+    remaining: f32,
+}
+
+impl JumpThrust {
+    pub fn new(accel: Scalar, fuel: f32) -> Self {
+        Self {
+            accel,
+            fuel,
+            remaining: fuel,
+        }
+    }
+
+    /// Seconds of thrust left before the controller has to land again.
+    pub fn remaining(&self) -> f32 {
+        self.remaining
+    }
+}
+
+/// Marks a controller that's still rising from a jump, until it's cut short or starts falling.
+#[derive(Component)]
+#[component(storage = "SparseSet")]
+struct JumpRising;
+
+/// How many extra jumps the controller can make before landing again.
+#[derive(Component, Clone, Copy, Debug, Default)]
+pub struct AirJumps(pub u8);
+
+/// The controller's jumps as of the latest step, for HUDs and debug readouts.
+#[derive(Component, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct JumpState {
+    /// Air jumps made since the controller last left the ground.
+    pub air_jumps_used: u8,
+    /// The controller's [`AirJumps`], or zero without them.
+    pub max_air_jumps: u8,
+    /// Whether a jump would happen right now, counting coyote time and air jumps.
+    pub can_jump: bool,
+}
+
+/// Snaps every jump's apex to a multiple of `unit`, so precision puzzles can rely on exact
+/// jump heights.
+#[derive(Component)]
+pub struct GridJump {
+    pub unit: Scalar,
+}
+
+impl GridJump {
+    /// The jump speed whose apex lands on the multiple of `unit` closest to the apex of a
+    /// regular jump, never less than one unit.
+    pub fn snapped_impulse(&self, jump_impulse: Scalar, gravity: Scalar) -> Scalar {
+        if self.unit <= 0.0 || gravity <= 0.0 {
+            return jump_impulse;
+        }
+
+        // Under constant gravity the apex height is v^2 / 2g, and the inverse gives the speed
+        let height = jump_impulse * jump_impulse / (2.0 * gravity);
+        let snapped_height = (height / self.unit).round().max(1.0) * self.unit;
+        (2.0 * gravity * snapped_height).sqrt()
+    }
+}
+
+/// Reflects the controller off walls it hits at a shallow angle and high speed instead of
+/// stopping it, preserving momentum for speedrun tech.
+///
+/// Slow or head-on hits still stop the controller as usual.
+#[derive(Component)]
+pub struct WallBounce {
+    pub min_speed: Scalar,
+    /// The fraction of the speed into the wall that is kept after bouncing.
+    pub restitution: Scalar,
+}
+
+impl WallBounce {
+    /// The velocity after bouncing off a wall with the given `normal`, or `None` if the hit
+    /// is too slow or too steep to bounce.
+    pub fn bounce(&self, velocity: Vector, normal: Vector) -> Option<Vector> {
+        let speed = velocity.length();
+        if speed < self.min_speed || speed == 0.0 {
+            return None;
+        }
+
+        // The sine of the angle between the velocity and the wall's surface
+        let incidence = -velocity.dot(normal) / speed;
+        if incidence <= 0.0 || incidence > WALL_BOUNCE_MAX_ANGLE.sin() {
+            return None;
+        }
+
+        Some(velocity - (1.0 + self.restitution) * velocity.dot(normal) * normal)
+    }
+}
+
+/// Lets an airborne controller grab ledges it moves towards while falling.
+///
+/// A ledge is a wall in front of the controller with open space above it, level with the top
+/// of the controller. While hanging, the controller ignores gravity and movement until
+/// [`MovementAction::Climb`] pulls it up onto the ledge, or [`MovementAction::Crouch`] or walking
+/// away lets go.
+#[derive(Component, Clone, Copy, Debug, Default)]
+pub struct LedgeGrab;
+
+/// The ledge a controller with [`LedgeGrab`] is hanging from.
+#[derive(Component)]
+#[component(storage = "SparseSet")]
+struct Hanging {
+    /// A point on top of the ledge, just past its edge.
+    ledge: Vector,
+    /// The direction of the ledge along the controller's right.
+    direction: Scalar,
+}
+
+/// Makes the controller shove dynamic rigid bodies it walks or jumps into, instead of treating
+/// them like immovable walls.
+#[derive(Component, Clone, Copy, Debug, Default)]
+pub struct PushDynamicBodies(pub bool);
+
+/// Nudges a rising controller sideways by up to this distance when it clips the corner of a
+/// ceiling, so jumps that only just catch a platform's edge carry on past it instead of stopping.
+#[derive(Component, Clone, Copy, Debug)]
+pub struct CornerCorrectionWidth(pub Scalar);
+
+/// Launches controllers that land on this surface upwards at `impulse` instead of stopping them.
+#[derive(Component, Clone, Copy, Debug)]
+pub struct BouncePad {
+    pub impulse: Scalar,
+}
+
+/// An optional horizontal dash with a cooldown.
+///
+/// A dash pressed while the cooldown is still running is buffered for `buffer_window` seconds
+/// and fires the moment the cooldown ends, so early presses aren't lost.
+#[derive(Component)]
+pub struct Dash {
+    pub speed: Scalar,
+    pub cooldown: f32,
+    pub buffer_window: f32,
+    cooldown_remaining: f32,
+    buffered_remaining: f32,
+    buffered_direction: Scalar,
+}
+
+impl Dash {
+    pub fn new(speed: Scalar, cooldown: f32, buffer_window: f32) -> Self {
+        Self {
+            speed,
+            cooldown,
+            buffer_window,
+            cooldown_remaining: 0.0,
+            buffered_remaining: 0.0,
+            buffered_direction: 0.0,
+        }
+    }
+
+    /// Dashes immediately if the cooldown has ended, otherwise buffers the request.
+    fn request(&mut self, direction: Scalar, up: Dir2, velocity: &mut LinearVelocity) {
+        if self.cooldown_remaining <= 0.0 {
+            self.fire(direction, up, velocity);
+        } else {
+            self.buffered_remaining = self.buffer_window;
+            self.buffered_direction = direction;
+        }
+    }
+
+    /// Clears the cooldown and any buffered dash.
+    fn reset(&mut self) {
+        self.cooldown_remaining = 0.0;
+        self.buffered_remaining = 0.0;
+    }
+
+    /// Sets the speed along the controller's right, perpendicular to `up`, to the dash speed.
+    fn fire(&mut self, direction: Scalar, up: Dir2, velocity: &mut LinearVelocity) {
+        let right = Vector::new(up.y, -up.x);
+        velocity.0 += (direction * self.speed - velocity.dot(right)) * right;
+        self.cooldown_remaining = self.cooldown;
+        self.buffered_remaining = 0.0;
+    }
+}
+
+/// The collision layers the controller collides with, so it can e.g. stand on terrain while
+/// passing through trigger zones.
+#[derive(Component, Clone, Copy, Debug)]
+pub struct CollisionLayerConfig {
+    pub mask: LayerMask,
+}
+
+impl Default for CollisionLayerConfig {
+    fn default() -> Self {
+        Self {
+            mask: LayerMask::ALL,
+        }
+    }
+}
+
+impl CollisionLayerConfig {
+    /// A filter for the controller's own shape casts.
+    pub fn filter(&self, controller: Entity) -> SpatialQueryFilter {
+        // Excluding the controller entity prevents controllers from colliding with themselves
+        SpatialQueryFilter::from_excluded_entities([controller]).with_mask(self.mask)
+    }
+}
+
+/// The collision layers a controller can stand on. Colliders on no other layer, like water or
+/// hazards, still block the controller but never count as ground.
+///
+/// Colliders without [`CollisionLayers`] are on the default layer.
+#[derive(Component, Clone, Copy, Debug)]
+pub struct GroundLayers(pub LayerMask);
+
+impl GroundLayers {
+    /// Whether a collider with the given `layers` counts as ground.
+    pub fn contains(&self, layers: Option<&CollisionLayers>) -> bool {
+        let memberships = layers.copied().unwrap_or_default().memberships;
+        memberships & self.0 != LayerMask::NONE
+    }
+}
+
+/// Extra colliders the controller's shape casts ignore, like hitboxes or sensors attached to it
+/// as children. The controller's own collider is always ignored.
+#[derive(Component, Clone, Debug, Default)]
+pub struct IgnoredColliders(pub Vec<Entity>);
+
+/// The filter for a controller's shape casts, including its [`IgnoredColliders`].
+fn controller_filter(
+    controller: Entity,
+    collision_layers: &CollisionLayerConfig,
+    ignored: Option<&IgnoredColliders>,
+) -> SpatialQueryFilter {
+    let filter = collision_layers.filter(controller);
+    match ignored {
+        Some(ignored) => filter.with_excluded_entities(ignored.0.iter().copied()),
+        None => filter,
+    }
+}
+
+/// What kind of surface a controller ran into.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ContactKind {
+    /// Flat enough to stand on.
+    Floor,
+    /// Too steep to stand on, in either direction.
+    Wall,
+    /// Facing down at least as steeply as a floor faces up.
+    Ceiling,
+}
+
+impl ContactKind {
+    /// Classifies a surface by the angle between its `normal` and `up`.
+    pub fn classify(normal: Vector, up: Dir2, max_slope_angle: Scalar) -> Self {
+        let alignment = normal.dot(*up);
+        let threshold = max_slope_angle.cos();
+
+        if alignment >= threshold {
+            Self::Floor
+        } else if alignment <= -threshold {
+            Self::Ceiling
+        } else {
+            Self::Wall
+        }
+    }
+}
+
+/// A surface a controller ran into during its latest collision response.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ControllerContact {
+    pub entity: Entity,
+    pub normal: Vector,
+    pub kind: ContactKind,
+}
+
+/// The surfaces a controller ran into during its latest collision response.
+#[derive(Component, Clone, Debug, Default)]
+pub struct ContactState(Vec<ControllerContact>);
+
+impl ContactState {
+    pub fn iter(&self) -> impl Iterator<Item = &ControllerContact> {
+        self.0.iter()
+    }
+
+    /// Whether any of the contacts is of the given kind.
+    pub fn touching(&self, kind: ContactKind) -> bool {
+        self.0.iter().any(|contact| contact.kind == kind)
+    }
+}
+
+/// Carries controllers standing on this surface along with it, like a conveyor belt.
+#[derive(Component, Clone, Copy, Debug)]
+pub struct SurfaceVelocity(pub Vector);
+
+/// Scales how much grip a surface gives controllers standing on it: ground damping takes away
+/// `friction` times the usual amount of speed, so ice is below 1.0 and mud above it.
+#[derive(Component, Clone, Copy, Debug)]
+pub struct SurfaceFriction(pub Scalar);
+
+impl SurfaceFriction {
+    /// The damping factor left after applying this friction to `damping`.
+    pub fn apply(&self, damping: Scalar) -> Scalar {
+        (1.0 - (1.0 - damping) * self.0.max(0.0)).clamp(0.0, 1.0)
+    }
+}
+
+/// The velocity the ground a controller is standing on carries it with: the ground's
+/// [`SurfaceVelocity`] plus, for moving platforms, the platform body's own velocity.
+#[derive(Component, Default)]
+struct GroundSurfaceVelocity(Vector);
+
+/// Half of the velocity gravity added in the latest step.
+///
+/// The controller moves by its average velocity over the step rather than its final one, which
+/// makes jump arcs the same at any timestep. The half step is taken back out for the collision
+/// response and put back before gravity is next applied, unless the motion was blocked.
+#[derive(Component, Default)]
+struct GravityHalfStep(Vector);
+
+/// A platform controllers can jump up through from below and land on from above.
+#[derive(Component, Clone, Copy, Debug, Default)]
+pub struct OneWayPlatform;
+
+/// The one-way platform a controller is dropping through, which its shape casts ignore until
+/// `remaining` runs out.
+#[derive(Component)]
+#[component(storage = "SparseSet")]
+struct DroppingThrough {
+    platform: Entity,
+    remaining: f32,
+}
+
+/// The collider a controller is standing on, or `None` while it's airborne.
+///
+/// Kept up to date by the controller, for gameplay like sticky platforms or hazardous floors.
+#[derive(Component, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct RidingPlatform(pub Option<Entity>);
+
+/// The [`Sensor`] colliders a controller currently overlaps.
+#[derive(Component, Default, Debug)]
+pub struct TriggerOverlaps(EntityHashSet);
+
+impl TriggerOverlaps {
+    pub fn contains(&self, trigger: Entity) -> bool {
+        self.0.contains(&trigger)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = Entity> + '_ {
+        self.0.iter().copied()
+    }
+}
+
+/// Marks a controller whose movement tuning should come from the plugin's [`ControllerTuning`].
+#[derive(Component)]
+#[component(storage = "SparseSet")]
+struct DefaultTuning(bool);
+
+/// A bundle that contains the components needed for a kinematic character controller.
+#[derive(Bundle)]
+pub struct CharacterControllerBundle {
+    character_controller: CharacterController,
+    player: PlayerIndex,
+    state: CharacterControllerState,
+    facing: Facing,
+    rigidbody: RigidBody,
+    collider: Collider,
+    ground_caster: ShapeCaster,
+    ground_caster_scale: GroundCasterScale,
+    gravity: Gravity,
+    gravity_enabled: GravityEnabled,
+    fall_gravity_multiplier: FallGravityMultiplier,
+    fast_fall_multiplier: FastFallMultiplier,
+    collision_layers: CollisionLayerConfig,
+    trigger_overlaps: TriggerOverlaps,
+    contact_state: ContactState,
+    ground_normal: GroundNormal,
+    ground_surface_velocity: GroundSurfaceVelocity,
+    gravity_half_step: GravityHalfStep,
+    riding_platform: RidingPlatform,
+    ungrounded_frames: UngroundedFrames,
+    air_time: AirTime,
+    speed: Speed,
+    fall_apex: FallApex,
+    coyote_time: CoyoteTime,
+    jump_state: JumpState,
+    jump_buffer: JumpBuffer,
+    terminal_velocity: TerminalVelocity,
+    air_acceleration: AirAcceleration,
+    air_damping: AirDamping,
+    movement: MovementBundle,
+    default_tuning: DefaultTuning,
+}
+
+/// A bundle that contains the tuning components for character movement.
+#[derive(Bundle)]
+pub struct MovementBundle {
+    acceleration: MovementAcceleration,
+    damping: MovementDamping,
+    jump_impulse: JumpImpulse,
+    max_slope_angle: MaxSlopeAngle,
+}
+
+impl MovementBundle {
+    pub const fn new(
+        acceleration: Scalar,
+        damping: Scalar,
+        jump_impulse: Scalar,
+        max_slope_angle: Scalar,
+    ) -> Self {
+        Self {
+            acceleration: MovementAcceleration(acceleration),
+            damping: MovementDamping(damping),
+            jump_impulse: JumpImpulse(jump_impulse),
+            max_slope_angle: MaxSlopeAngle(max_slope_angle),
+        }
+    }
+
+    /// Snappy movement that reaches full speed almost instantly and stops on a dime.
+    pub const fn precise() -> Self {
+        Self::new(2000.0, 0.8, 420.0, PI * 0.45)
+    }
+
+    /// Slow to get going and slow to stop, with a softer jump.
+    pub const fn floaty() -> Self {
+        Self::new(900.0, 0.96, 330.0, PI * 0.4)
+    }
+
+    /// Builds up speed gradually and keeps most of it, so the character slides on after input
+    /// is released.
+    pub const fn momentum() -> Self {
+        Self::new(600.0, 0.98, 400.0, PI * 0.45)
+    }
+}
+
+impl Default for MovementBundle {
+    fn default() -> Self {
+        ControllerTuning::default().into()
+    }
+}
+
+/// The values that make up a [`MovementBundle`], configured once on the plugin.
+#[derive(Resource, Clone, Copy, Debug)]
+pub struct ControllerTuning {
+    pub acceleration: Scalar,
+    pub damping: Scalar,
+    pub jump_impulse: Scalar,
+    pub max_slope_angle: Scalar,
+}
+
+impl Default for ControllerTuning {
+    fn default() -> Self {
+        Self {
+            acceleration: 1250.0,
+            damping: 0.92,
+            jump_impulse: 400.0,
+            max_slope_angle: PI * 0.45,
+        }
+    }
+}
+
+impl From<ControllerTuning> for MovementBundle {
+    fn from(tuning: ControllerTuning) -> Self {
+        Self::new(
+            tuning.acceleration,
+            tuning.damping,
+            tuning.jump_impulse,
+            tuning.max_slope_angle,
+        )
+    }
+}
+
+/// Everything needed to build a [`CharacterControllerBundle`] with a capsule collider, as plain
+/// values that level editors and save files can store.
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ControllerConfig {
+    pub capsule_radius: Scalar,
+    pub capsule_length: Scalar,
+    /// The strength of gravity, which pulls the controller down.
+    pub gravity: Scalar,
+    pub acceleration: Scalar,
+    pub damping: Scalar,
+    pub jump_impulse: Scalar,
+    pub max_slope_angle: Scalar,
+    pub terminal_velocity: Scalar,
+    pub air_acceleration: Scalar,
+    pub air_damping: Scalar,
+}
+
+impl Default for ControllerConfig {
+    fn default() -> Self {
+        let tuning = ControllerTuning::default();
+
+        Self {
+            capsule_radius: 15.0,
+            capsule_length: 30.0,
+            gravity: 1000.0,
+            acceleration: tuning.acceleration,
+            damping: tuning.damping,
+            jump_impulse: tuning.jump_impulse,
+            max_slope_angle: tuning.max_slope_angle,
+            terminal_velocity: TerminalVelocity::default().0,
+            air_acceleration: AirAcceleration::default().0,
+            air_damping: AirDamping::default().0,
+        }
+    }
+}
+
+/// An error from setting up a character controller.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ControllerError {
+    /// The collider isn't a solid convex shape, so it can't be swept through the world.
+    UnsupportedCollider(ShapeType),
+}
+
+impl fmt::Display for ControllerError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnsupportedCollider(shape) => write!(
+                f,
+                "{shape:?} colliders aren't supported by character controllers, \
+                 use a convex shape like a capsule instead"
+            ),
+        }
+    }
+}
+
+impl Error for ControllerError {}
+
+impl CharacterControllerBundle {
+    /// Like [`CharacterControllerBundle::new`], but rejects colliders the controller can't move
+    /// correctly, such as segments, polylines, meshes and compound shapes.
+    pub fn try_new(collider: Collider, gravity: Vector) -> Result<Self, ControllerError> {
+        match collider.shape().shape_type() {
+            ShapeType::Ball
+            | ShapeType::Cuboid
+            | ShapeType::Capsule
+            | ShapeType::Triangle
+            | ShapeType::ConvexPolygon
+            | ShapeType::RoundCuboid
+            | ShapeType::RoundTriangle
+            | ShapeType::RoundConvexPolygon => Ok(Self::new(collider, gravity)),
+            shape => Err(ControllerError::UnsupportedCollider(shape)),
+        }
+    }
+
+    /// A controller with an upright capsule collider that's `width` wide and `height` tall.
+    ///
+    /// A `height` less than `width` leaves no room for the capsule's straight section, so the
+    /// collider is a circle `height` across instead.
+    pub fn capsule(width: Scalar, height: Scalar, gravity: Vector) -> Self {
+        Self::new(
+            capsule_collider(Vector::new(width, height), CapsuleAxis::Vertical),
+            gravity,
+        )
+    }
+
+    /// Builds a capsule controller from a stored [`ControllerConfig`].
+    pub fn from_config(config: ControllerConfig) -> Self {
+        Self::new(
+            Collider::capsule(config.capsule_radius, config.capsule_length),
+            Vector::NEG_Y * config.gravity,
+        )
+        .with_movement(
+            config.acceleration,
+            config.damping,
+            config.jump_impulse,
+            config.max_slope_angle,
+        )
+        .with_terminal_velocity(config.terminal_velocity)
+        .with_air_acceleration(config.air_acceleration)
+        .with_air_damping(config.air_damping)
+    }
+
+    pub fn new(collider: Collider, gravity: Vector) -> Self {
+        let caster_shape = ground_caster_shape(&collider, GroundCasterScale::default());
+
+        Self {
+            character_controller: CharacterController,
+            player: PlayerIndex::default(),
+            state: CharacterControllerState::default(),
+            facing: Facing::default(),
+            rigidbody: RigidBody::Kinematic,
+            collider,
+            ground_caster: ShapeCaster::new(
+                caster_shape,
+                Vector::ZERO,
+                0.0,
+                -Gravity(gravity).up(),
+            )
+            .with_max_distance(10.0)
+            .with_max_hits(NORMAL_COUNT),
+            ground_caster_scale: GroundCasterScale::default(),
+            gravity: Gravity(gravity),
+            gravity_enabled: GravityEnabled::default(),
+            fall_gravity_multiplier: FallGravityMultiplier::default(),
+            fast_fall_multiplier: FastFallMultiplier::default(),
+            collision_layers: CollisionLayerConfig::default(),
+            trigger_overlaps: TriggerOverlaps::default(),
+            contact_state: ContactState::default(),
+            ground_normal: GroundNormal::default(),
+            ground_surface_velocity: GroundSurfaceVelocity::default(),
+            gravity_half_step: GravityHalfStep::default(),
+            riding_platform: RidingPlatform::default(),
+            ungrounded_frames: UngroundedFrames::default(),
+            air_time: AirTime::default(),
+            speed: Speed::default(),
+            fall_apex: FallApex::default(),
+            coyote_time: CoyoteTime::default(),
+            jump_state: JumpState::default(),
+            jump_buffer: JumpBuffer::default(),
+            terminal_velocity: TerminalVelocity::default(),
+            air_acceleration: AirAcceleration::default(),
+            air_damping: AirDamping::default(),
+            movement: MovementBundle::default(),
+            default_tuning: DefaultTuning(true),
+        }
+    }
+
+    pub fn with_movement(
+        mut self,
+        acceleration: Scalar,
+        damping: Scalar,
+        jump_impulse: Scalar,
+        max_slope_angle: Scalar,
+    ) -> Self {
+        self.movement = MovementBundle::new(acceleration, damping, jump_impulse, max_slope_angle);
+        self.default_tuning = DefaultTuning(false);
+        self
+    }
+
+    /// Only collides with, and stands on, colliders in the given layers.
+    pub fn with_collision_mask(mut self, mask: impl Into<LayerMask>) -> Self {
+        let mask = mask.into();
+        self.collision_layers = CollisionLayerConfig { mask };
+        self.ground_caster.query_filter.mask = mask;
+        self
+    }
+
+    pub fn with_terminal_velocity(mut self, terminal_velocity: Scalar) -> Self {
+        self.terminal_velocity = TerminalVelocity(terminal_velocity);
+        self
+    }
+
+    pub fn with_air_acceleration(mut self, air_acceleration: Scalar) -> Self {
+        self.air_acceleration = AirAcceleration(air_acceleration);
+        self
+    }
+
+    pub fn with_air_damping(mut self, air_damping: Scalar) -> Self {
+        self.air_damping = AirDamping(air_damping);
+        self
+    }
+
+    /// Collects up to `count` ground hits per step. More hits find the flattest ground more
+    /// reliably on uneven terrain, fewer are cheaper.
+    pub fn with_ground_normal_count(mut self, count: u32) -> Self {
+        self.ground_caster.max_hits = count.max(1);
+        self
+    }
+
+    /// Scales the ground caster's shape by `scale` relative to the collider, see
+    /// [`GroundCasterScale`].
+    pub fn with_ground_caster_scale(mut self, scale: Scalar) -> Self {
+        self.ground_caster_scale = GroundCasterScale(scale);
+        self.ground_caster.shape = ground_caster_shape(&self.collider, self.ground_caster_scale);
+        self
+    }
+
+    /// Drives the controller with the input of `player` instead of player 0.
+    pub fn with_player(mut self, player: u8) -> Self {
+        self.player = PlayerIndex(player);
+        self
+    }
+
+    /// Allows jumping for `duration` seconds after leaving the ground.
+    pub fn with_coyote_time(mut self, duration: f32) -> Self {
+        self.coyote_time = CoyoteTime::new(duration);
+        self
+    }
+
+    /// Keeps jumps pressed in the air for `duration` seconds, performing them on landing.
+    pub fn with_jump_buffer(mut self, duration: f32) -> Self {
+        self.jump_buffer = JumpBuffer::new(duration);
+        self
+    }
+
+    pub fn with_fall_gravity_multiplier(mut self, multiplier: Scalar) -> Self {
+        self.fall_gravity_multiplier = FallGravityMultiplier(multiplier);
+        self
+    }
+
+    /// Multiplies the strength of gravity without changing its direction.
+    pub fn with_gravity_scale(self, scale: Scalar) -> Self {
+        let gravity = self.gravity.0 * scale;
+        self.with_gravity(gravity)
+    }
+
+    /// Points gravity in `direction`, keeping its strength.
+    pub fn with_gravity_direction(self, direction: Dir2) -> Self {
+        let gravity = direction * self.gravity.0.length();
+        self.with_gravity(gravity)
+    }
+
+    fn with_gravity(mut self, gravity: Vector) -> Self {
+        self.gravity = Gravity(gravity);
+        self.ground_caster.direction = -self.gravity.up();
+        self
+    }
+}
+
+/// Which way a capsule's straight section runs.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CapsuleAxis {
+    Vertical,
+    Horizontal,
+}
+
+/// The capsule that fills a box of `size` with its straight section along `axis`, and the
+/// rotation that lays a `Capsule2d`, which is always vertical, along that axis.
+///
+/// A box that's shorter along `axis` than across it leaves no room for a straight section, so
+/// it gets a circle fitting its shorter side instead.
+pub fn capsule_from_size(size: Vector, axis: CapsuleAxis) -> (Capsule2d, Rot2) {
+    let (length, thickness, rotation) = match axis {
+        CapsuleAxis::Vertical => (size.y, size.x, Rot2::IDENTITY),
+        CapsuleAxis::Horizontal => (size.x, size.y, Rot2::FRAC_PI_2),
+    };
+    let radius = thickness.min(length) / 2.0;
+
+    (Capsule2d::new(radius, length - 2.0 * radius), rotation)
+}
+
+/// A collider for the capsule [`capsule_from_size`] fits in a box of `size`, laid along `axis`.
+pub fn capsule_collider(size: Vector, axis: CapsuleAxis) -> Collider {
+    let (capsule, rotation) = capsule_from_size(size, axis);
+    let half_segment = rotation * Vector::Y * capsule.half_length;
+    Collider::capsule_endpoints(capsule.radius, -half_segment, half_segment)
+}
+
+/// Spawns a controller with default tuning at `position` and returns its entity, so callers can
+/// attach followers, cameras, or tags to it.
+pub fn spawn_character_controller(
+    commands: &mut Commands,
+    collider: Collider,
+    position: Vector,
+    gravity: Vector,
+) -> Entity {
+    commands
+        .spawn((
+            CharacterControllerBundle::new(collider, gravity),
+            Transform::from_translation(position.extend(0.0)),
+        ))
+        .id()
+}
+
+/// Moves a controller to `position` without resolving collisions on the following physics step.
+///
+/// Unlike [`RespawnEvent`], the controller keeps its state, but it's stopped for the skipped step
+/// so it can't move into geometry while nothing is stopping it.
+pub fn teleport(commands: &mut Commands, controller: Entity, position: Vector) {
+    // Gravity's half step is dropped along with the old position, see `GravityHalfStep`
+    commands.entity(controller).insert((
+        Position(position),
+        GravityHalfStep::default(),
+        SkipCollision,
+    ));
+}
+
+fn apply_default_tuning(
+    mut commands: Commands,
+    tuning: Res<ControllerTuning>,
+    controllers: Query<(Entity, &DefaultTuning)>,
+) {
+    for (entity, default_tuning) in &controllers {
+        if default_tuning.0 {
+            commands
+                .entity(entity)
+                .insert(MovementBundle::from(*tuning));
+        }
+
+        // Tuning is only applied once so later changes to the components aren't overwritten
+        commands.entity(entity).remove::<DefaultTuning>();
+    }
+}
+
+/// The held keys of each player from the latest `Update` frame.
+///
+/// Held input is re-sent as events on every fixed step, so the controller accelerates the same
+/// amount per simulated second however many frames happen in between.
+#[derive(Resource, Default)]
+struct HeldInput(HashMap<u8, HeldKeys>);
+
+/// Presses from the `Update` frames since the latest fixed step.
+///
+/// Several frames can run between two fixed steps, so presses are queued here and all sent at the
+/// start of the next step instead of relying on the events outliving those frames.
+#[derive(Resource, Default)]
+struct InputQueue(Vec<ControllerAction>);
+
+struct HeldKeys {
+    direction: Scalar,
+    sprint: bool,
+    jump: bool,
+    climb: bool,
+    crouch: bool,
+    drop_through: bool,
+}
+
+fn keyboard_input(
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    default_bindings: Res<InputBindings>,
+    player_bindings: Query<(&PlayerIndex, &InputBindings)>,
+    mut held: ResMut<HeldInput>,
+    mut queue: ResMut<InputQueue>,
+) {
+    // Player 0 uses the resource unless one of its controllers has bindings of its own
+    let mut players = HashMap::from([(0, &*default_bindings)]);
+    players.extend(
+        player_bindings
+            .iter()
+            .map(|(player, bindings)| (player.0, bindings)),
+    );
+
+    held.0.clear();
+    for (player, bindings) in players {
+        let mut send = |action| queue.0.push(ControllerAction::player(player, action));
+
+        let left = keyboard_input.any_pressed(bindings.left.iter().copied());
+        let right = keyboard_input.any_pressed(bindings.right.iter().copied());
+        let direction = (right as i8 - left as i8) as Scalar;
+
+        let down = keyboard_input.any_pressed(bindings.down.iter().copied());
+        let jump = keyboard_input.any_just_pressed(bindings.jump.iter().copied());
+
+        held.0.insert(
+            player,
+            HeldKeys {
+                direction,
+                sprint: keyboard_input.any_pressed(bindings.sprint.iter().copied()),
+                jump: keyboard_input.any_pressed(bindings.jump.iter().copied()),
+                climb: keyboard_input.any_pressed(bindings.up.iter().copied()),
+                crouch: down,
+                drop_through: down && bindings.drop_through == DropThroughTrigger::HoldDown,
+            },
+        );
+
+        match bindings.drop_through {
+            DropThroughTrigger::HoldDown => {
+                if jump {
+                    send(MovementAction::Jump);
+                }
+            }
+            DropThroughTrigger::DownAndJump => {
+                if down && jump {
+                    send(MovementAction::DropThrough);
+                } else if jump {
+                    send(MovementAction::Jump);
+                }
+            }
+        }
+
+        if keyboard_input.any_just_pressed(bindings.dash.iter().copied()) && direction != 0.0 {
+            send(MovementAction::Dash(direction));
+        }
+
+        if keyboard_input.any_just_pressed(bindings.flip_gravity.iter().copied()) {
+            send(MovementAction::FlipGravity);
+        }
+    }
+}
+
+fn emit_input(
+    held: Res<HeldInput>,
+    mut queue: ResMut<InputQueue>,
+    mut action_events: EventWriter<ControllerAction>,
+) {
+    action_events.write_batch(queue.0.drain(..));
+
+    for (&player, keys) in &held.0 {
+        let mut send = |action| {
+            action_events.write(ControllerAction::player(player, action));
+        };
+
+        if keys.direction != 0.0 {
+            if keys.sprint {
+                send(MovementAction::Sprint(keys.direction));
+            } else {
+                send(MovementAction::Walk(keys.direction));
+            }
+        }
+
+        if keys.jump {
+            send(MovementAction::JumpHeld);
+        }
+
+        if keys.climb {
+            send(MovementAction::Climb);
+        }
+
+        if keys.crouch {
+            send(MovementAction::Crouch);
+            send(MovementAction::FastFall);
+        }
+
+        if keys.drop_through {
+            send(MovementAction::DropThrough);
+        }
+    }
+}
+
+fn apply_impulses(
+    mut impulse_events: EventReader<ApplyImpulse>,
+    mut controllers: Query<
+        (
+            &mut LinearVelocity,
+            &TerminalVelocity,
+            (&Gravity, Option<&UpDirection>),
+        ),
+        (With<CharacterController>, Without<Frozen>),
+    >,
+) {
+    for event in impulse_events.read() {
+        let Ok((mut velocity, terminal_velocity, (gravity, up_direction))) =
+            controllers.get_mut(event.entity)
+        else {
+            continue;
+        };
+
+        let up = controller_up(gravity, up_direction);
+        velocity.0 = clamp_fall(velocity.0 + event.impulse, up, terminal_velocity.0);
+    }
+}
+
+fn freeze(
+    mut commands: Commands,
+    mut controllers: Query<(Entity, &mut LinearVelocity), (Added<Frozen>, Without<FrozenVelocity>)>,
+) {
+    for (entity, mut velocity) in &mut controllers {
+        commands.entity(entity).insert(FrozenVelocity(velocity.0));
+        velocity.0 = Vector::ZERO;
+    }
+}
+
+fn thaw(
+    mut commands: Commands,
+    mut thawed: RemovedComponents<Frozen>,
+    mut controllers: Query<(&mut LinearVelocity, &FrozenVelocity), Without<Frozen>>,
+) {
+    for entity in thawed.read() {
+        let Ok((mut velocity, frozen_velocity)) = controllers.get_mut(entity) else {
+            continue;
+        };
+
+        velocity.0 = frozen_velocity.0;
+        commands.entity(entity).remove::<FrozenVelocity>();
+    }
+}
+
+fn drop_through(
+    mut commands: Commands,
+    mut action_events: EventReader<ControllerAction>,
+    mut controllers: Query<
+        (
+            Entity,
+            &PlayerIndex,
+            &RidingPlatform,
+            Option<&mut IgnoredColliders>,
+        ),
+        (With<Grounded>, Without<DroppingThrough>, Without<Frozen>),
+    >,
+    one_way_platforms: Query<(), With<OneWayPlatform>>,
+) {
+    let drops: Vec<ControllerAction> = action_events
+        .read()
+        .filter(|event| event.action == MovementAction::DropThrough)
+        .copied()
+        .collect();
+
+    for (entity, player, riding_platform, ignored) in &mut controllers {
+        if !drops.iter().any(|event| event.targets(entity, player)) {
+            continue;
+        }
+
+        let Some(platform) = riding_platform
+            .0
+            .filter(|&platform| one_way_platforms.contains(platform))
+        else {
+            continue;
+        };
+
+        // Ignoring the platform drops the controller out of the ground check, so gravity takes
+        // it down through the platform
+        match ignored {
+            Some(mut ignored) => ignored.0.push(platform),
+            None => {
+                commands
+                    .entity(entity)
+                    .insert(IgnoredColliders(vec![platform]));
+            }
+        }
+        commands.entity(entity).insert(DroppingThrough {
+            platform,
+            remaining: DROP_THROUGH_DURATION,
+        });
+    }
+}
+
+fn tick_drop_through(
+    time: Res<Time>,
+    mut commands: Commands,
+    mut controllers: Query<(Entity, &mut DroppingThrough, &mut IgnoredColliders), Without<Frozen>>,
+) {
+    for (entity, mut dropping_through, mut ignored) in &mut controllers {
+        dropping_through.remaining -= time.delta_secs();
+        if dropping_through.remaining > 0.0 {
+            continue;
+        }
+
+        // The controller may still be partway through, which one-way platforms already allow
+        if let Some(index) = ignored
+            .0
+            .iter()
+            .position(|&ignored| ignored == dropping_through.platform)
+        {
+            ignored.0.remove(index);
+        }
+        commands.entity(entity).remove::<DroppingThrough>();
+    }
+}
+
+fn flip_gravity(
+    mut action_events: EventReader<ControllerAction>,
+    mut controllers: Query<
+        (Entity, &PlayerIndex, &mut Gravity, Option<&mut UpDirection>),
+        Without<Frozen>,
+    >,
+) {
+    let flips: Vec<ControllerAction> = action_events
+        .read()
+        .filter(|event| event.action == MovementAction::FlipGravity)
+        .copied()
+        .collect();
+
+    for (entity, player, mut gravity, up_direction) in &mut controllers {
+        // Two flips in the same step cancel out
+        if flips
+            .iter()
+            .filter(|event| event.targets(entity, player))
+            .count()
+            % 2
+            == 0
+        {
+            continue;
+        }
+
+        gravity.0 = -gravity.0;
+        if let Some(mut up_direction) = up_direction {
+            up_direction.0 = -up_direction.0;
+        }
+    }
+}
+
+fn respawn(
+    mut commands: Commands,
+    mut respawn_events: EventReader<RespawnEvent>,
+    mut controllers: Query<
+        (
+            &mut Transform,
+            &mut Position,
+            &mut LinearVelocity,
+            &mut CharacterControllerState,
+            (&Gravity, Option<&UpDirection>),
+            (&mut GravityHalfStep, &mut GroundSurfaceVelocity),
+            (&mut AirTime, &mut FallApex, &mut UngroundedFrames),
+            (&mut CoyoteTime, &mut JumpBuffer, &mut JumpState),
+            Option<&mut JumpThrust>,
+            Option<&mut Dash>,
+            Option<&mut FrozenVelocity>,
+        ),
+        With<CharacterController>,
+    >,
+) {
+    for event in respawn_events.read() {
+        let Ok((
+            mut transform,
+            mut position,
+            mut velocity,
+            mut state,
+            (gravity, up_direction),
+            (mut half_step, mut surface_velocity),
+            (mut air_time, mut fall_apex, mut ungrounded_frames),
+            (mut coyote_time, mut jump_buffer, mut jump_state),
+            jump_thrust,
+            dash,
+            frozen_velocity,
+        )) = controllers.get_mut(event.entity)
+        else {
+            continue;
+        };
+
+        // Both are set so the teleport holds whether avian syncs from the transform or not
+        transform.translation = event.position.extend(transform.translation.z);
+        position.0 = event.position;
+        velocity.0 = Vector::ZERO;
+        *state = CharacterControllerState::default();
+        half_step.0 = Vector::ZERO;
+        surface_velocity.0 = Vector::ZERO;
+        air_time.0 = 0.0;
+        // Falls are measured from the respawn point, not from wherever the controller was
+        fall_apex.0 = Some(event.position.dot(*controller_up(gravity, up_direction)));
+        ungrounded_frames.0 = 0;
+        coyote_time.remaining = 0.0;
+        jump_buffer.remaining = 0.0;
+        jump_state.air_jumps_used = 0;
+        if let Some(mut jump_thrust) = jump_thrust {
+            jump_thrust.remaining = jump_thrust.fuel;
+        }
+        if let Some(mut dash) = dash {
+            dash.reset();
+        }
+        if let Some(mut frozen_velocity) = frozen_velocity {
+            frozen_velocity.0 = Vector::ZERO;
+        }
+
+        // Grounded is recomputed from the ground caster at the new position
+        commands
+            .entity(event.entity)
+            .remove::<(Grounded, HardLanding, Hanging, JumpRising)>();
+    }
+}
+
+fn reset_controllers(
+    mut commands: Commands,
+    mut reset_events: EventReader<ResetControllersEvent>,
+    mut controllers: Query<
+        (
+            Entity,
+            &mut LinearVelocity,
+            &mut CharacterControllerState,
+            (&mut GravityHalfStep, &mut GroundSurfaceVelocity),
+            (&mut AirTime, &mut FallApex, &mut UngroundedFrames),
+            (&mut CoyoteTime, &mut JumpBuffer, &mut JumpState),
+            Option<&mut JumpThrust>,
+            Option<&mut Dash>,
+            Option<&mut FrozenVelocity>,
+            Option<&DroppingThrough>,
+            Option<&mut IgnoredColliders>,
+        ),
+        With<CharacterController>,
+    >,
+) {
+    if reset_events.is_empty() {
+        return;
+    }
+    reset_events.clear();
+
+    for (
+        entity,
+        mut velocity,
+        mut state,
+        (mut half_step, mut surface_velocity),
+        (mut air_time, mut fall_apex, mut ungrounded_frames),
+        (mut coyote_time, mut jump_buffer, mut jump_state),
+        jump_thrust,
+        dash,
+        frozen_velocity,
+        dropping_through,
+        ignored,
+    ) in &mut controllers
+    {
+        velocity.0 = Vector::ZERO;
+        *state = CharacterControllerState::default();
+        half_step.0 = Vector::ZERO;
+        surface_velocity.0 = Vector::ZERO;
+        air_time.0 = 0.0;
+        fall_apex.0 = None;
+        ungrounded_frames.0 = 0;
+        coyote_time.remaining = 0.0;
+        jump_buffer.remaining = 0.0;
+        jump_state.air_jumps_used = 0;
+        if let Some(mut jump_thrust) = jump_thrust {
+            jump_thrust.remaining = jump_thrust.fuel;
+        }
+        if let Some(mut dash) = dash {
+            dash.reset();
+        }
+        // Frozen controllers stay frozen, but don't pick their old motion back up when thawed
+        if let Some(mut frozen_velocity) = frozen_velocity {
+            frozen_velocity.0 = Vector::ZERO;
+        }
+        if let (Some(dropping_through), Some(mut ignored)) = (dropping_through, ignored) {
+            ignored
+                .0
+                .retain(|&ignored| ignored != dropping_through.platform);
+        }
+
+        // Grounded is recomputed from the ground caster on the next step
+        commands
+            .entity(entity)
+            .remove::<(Grounded, HardLanding, Hanging, JumpRising, DroppingThrough)>();
+    }
+}
+
+fn change_collider(
+    mut change_events: EventReader<ChangeColliderEvent>,
+    mut controllers: Query<
+        (
+            &mut Collider,
+            (&mut ShapeCaster, &GroundCasterScale),
+            &mut Transform,
+            &mut Position,
+            &Rotation,
+            (&Gravity, Option<&UpDirection>),
+        ),
+        With<CharacterController>,
+    >,
+) {
+    for event in change_events.read() {
+        let Ok((
+            mut collider,
+            (mut ground_caster, caster_scale),
+            mut transform,
+            mut position,
+            rotation,
+            (gravity, up_direction),
+        )) = controllers.get_mut(event.entity)
+        else {
+            continue;
+        };
+
+        // How far each collider reaches below the controller's center
+        let up = controller_up(gravity, up_direction);
+        let depth = |collider: &Collider| {
+            let aabb = collider.aabb(Vector::ZERO, *rotation);
+            let half_size = (aabb.max - aabb.min) / 2.0;
+            half_size.dot(up.abs()) - (aabb.min + aabb.max).dot(*up) / 2.0
+        };
+        let offset = (depth(&event.collider) - depth(&collider)) * up;
+
+        // Both are set so the move holds whether avian syncs from the transform or not
+        position.0 += offset;
+        transform.translation += offset.extend(0.0);
+        ground_caster.shape = ground_caster_shape(&event.collider, *caster_scale);
+        *collider = event.collider.clone();
+    }
+}
+
+fn update_grounded(
+    mut commands: Commands,
+    mut controllers: Query<
+        (
+            Entity,
+            &ShapeHits,
+            &mut ShapeCaster,
+            &Rotation,
+            (&Gravity, Option<&UpDirection>),
+            &MaxSlopeAngle,
+            &mut GroundNormal,
+            &mut GroundSurfaceVelocity,
+            &mut RidingPlatform,
+            &mut UngroundedFrames,
+            Option<&GroundedGrace>,
+            Option<Ref<IgnoredColliders>>,
+            Option<&GroundLayers>,
+        ),
+        With<CharacterController>,
+    >,
+    grounds: Query<(
+        Option<&SurfaceVelocity>,
+        Option<&ColliderOf>,
+        Option<&CollisionLayers>,
+        Has<OneWayPlatform>,
+    )>,
+    platforms: Query<&LinearVelocity, Without<CharacterController>>,
+) {
+    for (
+        entity,
+        hits,
+        mut ground_caster,
+        rotation,
+        (gravity, up_direction),
+        max_slope_angle,
+        mut ground_normal,
+        mut ground_surface_velocity,
+        mut riding_platform,
+        mut ungrounded_frames,
+        grace,
+        ignored,
+        ground_layers,
+    ) in &mut controllers
+    {
+        let up = controller_up(gravity, up_direction);
+
+        if let Some(ignored) = ignored.filter(|ignored| ignored.is_changed()) {
+            ground_caster.query_filter.excluded_entities = ignored.0.iter().copied().collect();
+        }
+
+        // The caster is in local space, so it's rotated back to keep casting along gravity
+        let cast_direction = Dir2::new(rotation.inverse() * -*up).unwrap_or(Dir2::NEG_Y);
+        if ground_caster.direction != cast_direction {
+            ground_caster.direction = cast_direction;
+        }
+
+        // The controller is grounded if the ground caster hits a surface that isn't too steep.
+        // Of those, the flattest one is the ground, so a steep side of a corner can't win.
+        // One-way platforms the controller is still passing through don't count, and neither do
+        // colliders outside its ground layers.
+        let ground = hits
+            .iter()
+            .filter(|hit| {
+                hit.distance > 0.0 || !grounds.get(hit.entity).is_ok_and(|(.., one_way)| one_way)
+            })
+            .filter(|hit| {
+                ground_layers.is_none_or(|ground_layers| {
+                    ground_layers.contains(
+                        grounds
+                            .get(hit.entity)
+                            .ok()
+                            .and_then(|(.., layers, _)| layers),
+                    )
+                })
+            })
+            .filter_map(|hit| Some((hit, Dir2::new(rotation * -hit.normal2).ok()?)))
+            .filter(|(_, normal)| normal.angle_to(*up).abs() <= max_slope_angle.0)
+            .max_by(|(_, a), (_, b)| a.dot(*up).total_cmp(&b.dot(*up)));
+
+        riding_platform.set_if_neq(RidingPlatform(ground.map(|(hit, _)| hit.entity)));
+        ground_surface_velocity.0 = riding_platform
+            .0
+            .and_then(|platform| grounds.get(platform).ok())
+            .map_or(Vector::ZERO, |(surface_velocity, collider_of, ..)| {
+                let body_velocity = collider_of
+                    .and_then(|collider_of| platforms.get(collider_of.body).ok())
+                    .map_or(Vector::ZERO, |velocity| velocity.0);
+                surface_velocity.map_or(Vector::ZERO, |surface_velocity| surface_velocity.0)
+                    + body_velocity
+            });
+
+        if let Some((_, normal)) = ground {
+            ground_normal.set_if_neq(GroundNormal(Some(normal)));
+            ungrounded_frames.0 = 0;
+            commands.entity(entity).insert(Grounded);
+        } else {
+            ungrounded_frames.0 = ungrounded_frames.0.saturating_add(1);
+            if ungrounded_frames.0 > grace.map_or(0, |grace| grace.0) {
+                ground_normal.set_if_neq(GroundNormal(None));
+                commands.entity(entity).remove::<Grounded>();
+            }
+        }
+    }
+}
+
+/// The controller state that [`integrate_velocity`] starts from, and returns after one step.
+#[derive(Clone, Copy, Debug)]
+pub struct VelocityState {
+    pub velocity: Vector,
+    /// Half of the velocity gravity added in the latest step, which the controller hasn't moved
+    /// by yet.
+    pub half_step: Vector,
+    pub grounded: bool,
+    /// The friction of the ground the controller stands on, if it has any.
+    pub friction: Option<SurfaceFriction>,
+}
+
+/// The movement input applied during one [`integrate_velocity`] step.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct VelocityInput {
+    pub direction: Scalar,
+    pub sprint: bool,
+    pub jump: bool,
+    pub fast_fall: bool,
+}
+
+/// The tuning values [`integrate_velocity`] depends on, mirroring the controller's components.
+#[derive(Clone, Copy, Debug)]
+pub struct VelocityTuning {
+    pub gravity: GravityTuning,
+    pub acceleration: Scalar,
+    pub air_acceleration: Scalar,
+    pub damping: Scalar,
+    pub air_damping: Scalar,
+    pub curve: MovementCurve,
+    pub jump_impulse: Scalar,
+    /// The controller's [`MaxHorizontalSpeed`], if it has one.
+    pub max_horizontal_speed: Option<Scalar>,
+}
+
+/// How gravity pulls on a controller, mirroring its gravity components.
+#[derive(Clone, Copy, Debug)]
+pub struct GravityTuning {
+    pub gravity: Vector,
+    /// The controller's up, which is opposite to `gravity` unless it has an [`UpDirection`].
+    pub up: Dir2,
+    pub enabled: bool,
+    pub terminal_velocity: Scalar,
+    pub fall_multiplier: Scalar,
+    pub fast_fall_multiplier: Scalar,
+    /// The controller's [`JumpSnappiness`], if it has one.
+    pub snappiness: Option<Scalar>,
+    /// The controller's [`ApexGravityMultiplier`] and [`ApexThreshold`], if it has them.
+    pub apex: Option<(Scalar, Scalar)>,
+}
+
+impl GravityTuning {
+    /// The multiplier on gravity for a controller moving at `vertical_speed` along up.
+    pub fn multiplier(&self, vertical_speed: Scalar, grounded: bool, fast_falling: bool) -> Scalar {
+        let mut multiplier = self.snappiness.map_or(1.0, |snappiness| {
+            JumpSnappiness(snappiness).gravity_multiplier(vertical_speed)
+        });
+
+        let at_apex =
+            |(_, threshold): &(Scalar, Scalar)| !grounded && vertical_speed.abs() < *threshold;
+        if let Some((apex_multiplier, _)) = self.apex.filter(at_apex) {
+            multiplier *= apex_multiplier;
+        } else if vertical_speed < 0.0 {
+            multiplier *= self.fall_multiplier;
+        }
+
+        if fast_falling && !grounded {
+            multiplier *= self.fast_fall_multiplier;
+        }
+        multiplier
+    }
+
+    /// Applies one step of gravity to `velocity`, returning the new velocity and the new half
+    /// step, see [`VelocityState::half_step`].
+    ///
+    /// The previous step's `half_step` is put back first, so the multiplier is picked for the
+    /// speed the controller is actually moving at.
+    pub fn step(
+        &self,
+        (velocity, half_step): (Vector, Vector),
+        grounded: bool,
+        fast_falling: bool,
+        delta_secs: Scalar,
+    ) -> (Vector, Vector) {
+        let velocity = velocity + half_step;
+        if !self.enabled {
+            return (velocity, Vector::ZERO);
+        }
+
+        let multiplier = self.multiplier(velocity.dot(*self.up), grounded, fast_falling);
+        let next = gravity_step(
+            velocity,
+            self.gravity * multiplier,
+            self.up,
+            self.terminal_velocity,
+            delta_secs,
+        );
+        (next, (next - velocity) * 0.5)
+    }
+}
+
+/// Computes the controller's next state the same way its systems do: gravity first, then input
+/// acceleration and jumping, then damping and the horizontal speed limit.
+///
+/// Collisions, dashes, coyote time, air jumps and grid jumps aren't part of this, so it's mostly
+/// useful for testing the feel math and for predicting trajectories.
+pub fn integrate_velocity(
+    state: VelocityState,
+    input: VelocityInput,
+    delta_secs: Scalar,
+    tuning: &VelocityTuning,
+) -> VelocityState {
+    let up = tuning.gravity.up;
+    let (mut velocity, half_step) = tuning.gravity.step(
+        (state.velocity, state.half_step),
+        state.grounded,
+        input.fast_fall,
+        delta_secs,
+    );
+
+    let acceleration = if state.grounded {
+        tuning.acceleration
+    } else {
+        tuning.air_acceleration
+    };
+    let damping = effective_damping(
+        (tuning.damping, tuning.air_damping),
+        state.friction.as_ref(),
+        state.grounded,
+    );
+    velocity = walk_velocity(
+        velocity,
+        up,
+        (input.direction, input.sprint),
+        (acceleration, damping, tuning.curve),
+        delta_secs,
+    );
+
+    if input.jump && state.grounded {
+        velocity = jump_velocity(velocity, half_step, up, tuning.jump_impulse);
+    }
+
+    velocity = damping_step(velocity, damping, up, delta_secs);
+    if let Some(max_speed) = tuning.max_horizontal_speed {
+        velocity = clamp_horizontal(velocity, up, max_speed);
+    }
+
+    VelocityState {
+        velocity,
+        half_step,
+        ..state
+    }
+}
+
+/// Applies `gravity` for `delta_secs`, never falling faster than `terminal_velocity` against
+/// `up`.
+pub fn gravity_step(
+    velocity: Vector,
+    gravity: Vector,
+    up: Dir2,
+    terminal_velocity: Scalar,
+    delta_secs: Scalar,
+) -> Vector {
+    // Unbounded falls would eventually tunnel through thin platforms
+    clamp_fall(velocity + gravity * delta_secs, up, terminal_velocity)
+}
+
+/// Slows `velocity` down to `terminal_velocity` if it's moving against `up` any faster,
+/// leaving the rest of it alone.
+fn clamp_fall(velocity: Vector, up: Dir2, terminal_velocity: Scalar) -> Vector {
+    let fall = -velocity.dot(*up);
+    if fall > terminal_velocity {
+        velocity + (fall - terminal_velocity) * up
+    } else {
+        velocity
+    }
+}
+
+/// The horizontal speed gained by walking or sprinting in `direction` for `delta_secs`.
+pub fn walk_step(
+    direction: Scalar,
+    acceleration: Scalar,
+    sprint: bool,
+    delta_secs: Scalar,
+) -> Scalar {
+    let multiplier = if sprint { SPRINT_MULTIPLIER } else { 1.0 };
+    direction * acceleration * multiplier * delta_secs
+}
+
+/// Walks or sprints in `direction` along the controller's right, perpendicular to `up`, for
+/// `delta_secs`.
+///
+/// The `curve` shapes the acceleration towards the top speed that `damping` lets walking at
+/// `acceleration` reach.
+pub fn walk_velocity(
+    velocity: Vector,
+    up: Dir2,
+    (direction, sprint): (Scalar, bool),
+    (acceleration, damping, curve): (Scalar, Scalar, MovementCurve),
+    delta_secs: Scalar,
+) -> Vector {
+    let top_speed = top_walk_speed(walk_step(1.0, acceleration, sprint, 1.0), damping);
+    let scale = curve_scale(&curve, velocity, up, direction, top_speed);
+
+    let right = Vector::new(up.y, -up.x);
+    velocity + right * walk_step(direction, acceleration, sprint, delta_secs) * scale
+}
+
+/// Replaces the speed along `up` with a jump at `impulse`, see [`jump`].
+fn jump_velocity(velocity: Vector, half_step: Vector, up: Dir2, impulse: Scalar) -> Vector {
+    let step_gravity = 2.0 * half_step.dot(*up);
+    velocity + (impulse + step_gravity - velocity.dot(*up)) * up
+}
+
+/// The damping a controller moves with: its ground damping, scaled by the [`SurfaceFriction`] of
+/// the ground if it has any, or its air damping while airborne.
+pub fn effective_damping(
+    (ground_damping, air_damping): (Scalar, Scalar),
+    friction: Option<&SurfaceFriction>,
+    grounded: bool,
+) -> Scalar {
+    if grounded {
+        friction.map_or(ground_damping, |friction| friction.apply(ground_damping))
+    } else {
+        air_damping
+    }
+}
+
+/// Damps motion across `up` for `delta_secs`, leaving motion along `up` to gravity.
+///
+/// `damping` is the factor kept per 1/60th of a second. It's applied exponentially, so the decay
+/// over a given amount of time is the same at any frame rate.
+pub fn damping_step(velocity: Vector, damping: Scalar, up: Dir2, delta_secs: Scalar) -> Vector {
+    let vertical = velocity.project_onto_normalized(*up);
+    vertical + (velocity - vertical) * damping.powf(delta_secs * DAMPING_REFERENCE_RATE)
+}
+
+/// Limits the speed along the controller's right, perpendicular to `up`, to `max_speed`, leaving
+/// the speed along `up` alone.
+pub fn clamp_horizontal(velocity: Vector, up: Dir2, max_speed: Scalar) -> Vector {
+    let right = Vector::new(up.y, -up.x);
+    let speed = velocity.dot(right);
+    velocity + (speed.clamp(-max_speed, max_speed) - speed) * right
+}
+
+/// A speed below which [`is_moving`] and [`is_idle`] treat a controller as standing still, for
+/// when damping leaves a tiny leftover speed.
+pub const IDLE_SPEED_EPSILON: Scalar = 1.0;
+
+/// Whether a controller moving at `velocity` is faster than `epsilon`, e.g.
+/// [`IDLE_SPEED_EPSILON`].
+pub fn is_moving(velocity: Vector, epsilon: Scalar) -> bool {
+    velocity.length() > epsilon
+}
+
+/// Whether a controller is standing still on the ground, i.e. `grounded` and not
+/// [`is_moving`].
+pub fn is_idle(velocity: Vector, grounded: bool, epsilon: Scalar) -> bool {
+    grounded && !is_moving(velocity, epsilon)
+}
+
+fn apply_gravity(
+    time: Res<Time>,
+    mut action_events: EventReader<ControllerAction>,
+    mut controllers: Query<
+        (
+            (Entity, &PlayerIndex),
+            (&Gravity, Option<&UpDirection>),
+            &GravityEnabled,
+            &TerminalVelocity,
+            &FallGravityMultiplier,
+            &FastFallMultiplier,
+            (&mut LinearVelocity, &mut GravityHalfStep),
+            Option<&JumpSnappiness>,
+            Option<(&ApexGravityMultiplier, &ApexThreshold)>,
+            Has<Grounded>,
+        ),
+        (Without<Frozen>, Without<Hanging>),
+    >,
+) {
+    let delta_secs = time.delta_secs();
+    // Every event is read so none are left over for the next step
+    let fast_falls: Vec<ControllerAction> = action_events
+        .read()
+        .filter(|event| event.action == MovementAction::FastFall)
+        .copied()
+        .collect();
+
+    for (
+        (entity, player),
+        (gravity, up_direction),
+        gravity_enabled,
+        terminal_velocity,
+        fall_multiplier,
+        fast_fall_multiplier,
+        (mut velocity, mut half_step),
+        snappiness,
+        apex,
+        grounded,
+    ) in &mut controllers
+    {
+        let tuning = GravityTuning {
+            gravity: gravity.0,
+            up: controller_up(gravity, up_direction),
+            enabled: gravity_enabled.0,
+            terminal_velocity: terminal_velocity.0,
+            fall_multiplier: fall_multiplier.0,
+            fast_fall_multiplier: fast_fall_multiplier.0,
+            snappiness: snappiness.map(|snappiness| snappiness.0),
+            apex: apex.map(|(multiplier, threshold)| (multiplier.0, threshold.0)),
+        };
+        let fast_falling = fast_falls.iter().any(|event| event.targets(entity, player));
+
+        let (next_velocity, next_half_step) = tuning.step(
+            (velocity.0, half_step.0),
+            grounded,
+            fast_falling,
+            delta_secs,
+        );
+        velocity.0 = next_velocity;
+        half_step.0 = next_half_step;
+    }
+}
+
+fn tick_dash(
+    time: Res<Time>,
+    mut controllers: Query<
+        (
+            &mut Dash,
+            (&Gravity, Option<&UpDirection>),
+            &mut LinearVelocity,
+        ),
+        Without<Frozen>,
+    >,
+) {
+    let delta_secs = time.delta_secs();
+
+    for (mut dash, (gravity, up_direction), mut velocity) in &mut controllers {
+        dash.cooldown_remaining = (dash.cooldown_remaining - delta_secs).max(0.0);
+        dash.buffered_remaining = (dash.buffered_remaining - delta_secs).max(0.0);
+
+        // Checked after ticking so a buffered dash fires on the same frame the cooldown ends
+        if dash.cooldown_remaining <= 0.0 && dash.buffered_remaining > 0.0 {
+            let direction = dash.buffered_direction;
+            let up = controller_up(gravity, up_direction);
+            dash.fire(direction, up, &mut velocity);
+        }
+    }
+}
+
+fn tick_jump_timers(
+    time: Res<Time>,
+    mut controllers: Query<
+        (
+            &mut CoyoteTime,
+            &mut JumpBuffer,
+            &mut JumpState,
+            Has<Grounded>,
+        ),
+        Without<Frozen>,
+    >,
+) {
+    let delta_secs = time.delta_secs();
+
+    for (mut coyote_time, mut jump_buffer, mut jump_state, is_grounded) in &mut controllers {
+        if is_grounded {
+            jump_state.air_jumps_used = 0;
+        }
+        coyote_time.remaining = if is_grounded {
+            coyote_time.duration
+        } else {
+            (coyote_time.remaining - delta_secs).max(0.0)
+        };
+        jump_buffer.remaining = (jump_buffer.remaining - delta_secs).max(0.0);
+    }
+}
+
+/// Sets the controller's speed along its up direction to that of a jump.
+///
+/// Gravity has already been applied this step, to the speed the jump replaces, so it's applied
+/// again on top of the jump. That way the [`GravityHalfStep`] belongs to the jump rather than to
+/// the old speed, and jump arcs stay the same at any timestep.
+fn jump(
+    (velocity, half_step): (&mut LinearVelocity, &GravityHalfStep),
+    (jump_impulse, max_height): (&JumpImpulse, Option<&MaxJumpHeight>),
+    (gravity, up_direction): (&Gravity, Option<&UpDirection>),
+    rising_gravity: &RisingGravity,
+    speed_bonus: Option<&JumpSpeedBonus>,
+    grid_jump: Option<&GridJump>,
+) {
+    let up = controller_up(gravity, up_direction);
+    let running_speed = velocity.dot(Vector::new(up.y, -up.x)).abs();
+    let base_impulse = max_height.map_or(jump_impulse.0, |max_height| {
+        rising_gravity.jump_speed_for_height(max_height.0)
+    });
+    let impulse = base_impulse + speed_bonus.map_or(0.0, |bonus| running_speed * bonus.0);
+    let impulse = match grid_jump {
+        Some(grid_jump) => grid_jump.snapped_impulse(impulse, gravity.0.length()),
+        None => impulse,
+    };
+
+    // Jumps replace the vertical speed along up, so flipped controllers jump towards their
+    // own up
+    velocity.0 = jump_velocity(velocity.0, half_step.0, up, impulse);
+}
+
+/// Leans a jump that was just made from the ground with the controller's [`SlopeJump`].
+fn lean_off_slope(
+    velocity: &mut LinearVelocity,
+    (gravity, up_direction): (&Gravity, Option<&UpDirection>),
+    (ground_normal, slope_jump): (&GroundNormal, Option<&SlopeJump>),
+) {
+    if let (Some(slope_jump), Some(normal)) = (slope_jump, ground_normal.0) {
+        slope_jump.redirect(velocity, controller_up(gravity, up_direction), normal);
+    }
+}
+
+/// The speed a jump needs to rise `height` under constant `gravity`.
+pub fn jump_speed_for_height(height: Scalar, gravity: Scalar) -> Scalar {
+    (2.0 * gravity * height.max(0.0)).sqrt()
+}
+
+/// The gravity slowing a rising controller, scaled at each speed the way [`apply_gravity`] scales
+/// it, so jump heights come out right with snappiness, apex hang time or fast falling.
+struct RisingGravity<'a> {
+    gravity: Scalar,
+    snappiness: Option<&'a JumpSnappiness>,
+    apex: Option<(&'a ApexGravityMultiplier, &'a ApexThreshold)>,
+    fast_fall: Scalar,
+}
+
+impl<'a> RisingGravity<'a> {
+    fn new(
+        gravity: &Gravity,
+        (snappiness, apex, fast_fall_multiplier): (
+            Option<&'a JumpSnappiness>,
+            Option<(&'a ApexGravityMultiplier, &'a ApexThreshold)>,
+            &FastFallMultiplier,
+        ),
+        fast_falling: bool,
+    ) -> Self {
+        Self {
+            gravity: gravity.0.length(),
+            snappiness,
+            apex,
+            fast_fall: if fast_falling {
+                fast_fall_multiplier.0
+            } else {
+                1.0
+            },
+        }
+    }
+
+    /// The gravity at `speed` along up.
+    fn at(&self, speed: Scalar) -> Scalar {
+        let mut multiplier = self
+            .snappiness
+            .map_or(1.0, |snappiness| snappiness.gravity_multiplier(speed));
+        if let Some((apex_multiplier, _)) = self.apex.filter(|(_, threshold)| speed < threshold.0) {
+            multiplier *= apex_multiplier.0;
+        }
+
+        self.gravity * multiplier * self.fast_fall
+    }
+
+    /// The speed a jump needs to rise `height`.
+    ///
+    /// Gravity only changes at the apex thresholds, so the rise is split into bands of constant
+    /// gravity that are climbed from the apex down until `height` is used up.
+    fn jump_speed_for_height(&self, height: Scalar) -> Scalar {
+        let mut edges: Vec<Scalar> = [
+            self.snappiness.map(|_| APEX_SPEED_THRESHOLD),
+            self.apex.map(|(_, threshold)| threshold.0),
+        ]
+        .into_iter()
+        .flatten()
+        .filter(|&edge| edge > 0.0)
+        .collect();
+        edges.sort_by(Scalar::total_cmp);
+        edges.push(Scalar::INFINITY);
+
+        let mut speed: Scalar = 0.0;
+        let mut height = height.max(0.0);
+        for edge in edges {
+            let gravity = self.at(speed);
+            let band_height = (edge * edge - speed * speed) / (2.0 * gravity);
+            if height <= band_height {
+                return (speed * speed + 2.0 * gravity * height).sqrt();
+            }
+            height -= band_height;
+            speed = edge;
+        }
+
+        speed
+    }
+}
+
+fn movement(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut action_events: EventReader<ControllerAction>,
+    mut controllers: Query<
+        (
+            (Entity, &PlayerIndex),
+            &MovementAcceleration,
+            &AirAcceleration,
+            (
+                &MovementDamping,
+                &AirDamping,
+                Option<&MovementCurve>,
+                &RidingPlatform,
+            ),
+            (&JumpImpulse, Option<&MaxJumpHeight>),
+            (&Gravity, Option<&UpDirection>),
+            (
+                Option<&JumpSnappiness>,
+                Option<(&ApexGravityMultiplier, &ApexThreshold)>,
+                &FastFallMultiplier,
+            ),
+            (&mut LinearVelocity, &GravityHalfStep),
+            Option<&JumpSpeedBonus>,
+            Option<&GridJump>,
+            Option<&mut Dash>,
+            (
+                &mut CoyoteTime,
+                &mut JumpBuffer,
+                &mut JumpState,
+                Option<&AirJumps>,
+            ),
+            (&GroundNormal, Option<&SlopeJump>),
+            Has<Grounded>,
+        ),
+        (Without<Frozen>, Without<Hanging>),
+    >,
+    surfaces: Query<&SurfaceFriction>,
+) {
+    let delta_secs = time.delta_secs();
+    let actions: Vec<ControllerAction> = action_events.read().copied().collect();
+    let fast_falling = |entity: Entity, player: &PlayerIndex| {
+        actions
+            .iter()
+            .any(|event| event.action == MovementAction::FastFall && event.targets(entity, player))
+    };
+
+    for event in &actions {
+        for (
+            (entity, player),
+            ground_acceleration,
+            air_acceleration,
+            (ground_damping, air_damping, curve, riding_platform),
+            jump_impulse,
+            gravity,
+            gravity_multipliers,
+            (mut velocity, half_step),
+            speed_bonus,
+            grid_jump,
+            dash,
+            (mut coyote_time, mut jump_buffer, mut jump_state, air_jumps),
+            slope,
+            is_grounded,
+        ) in &mut controllers
+        {
+            if !event.targets(entity, player) {
+                continue;
+            }
+
+            let acceleration = if is_grounded {
+                ground_acceleration.0
+            } else {
+                air_acceleration.0
+            };
+            // The top speed the movement curve works towards depends on the ground's grip
+            let friction = riding_platform
+                .0
+                .and_then(|ground| surfaces.get(ground).ok());
+            let damping =
+                effective_damping((ground_damping.0, air_damping.0), friction, is_grounded);
+
+            match &event.action {
+                MovementAction::Walk(direction) | MovementAction::Sprint(direction) => {
+                    let sprint = matches!(event.action, MovementAction::Sprint(_));
+                    velocity.0 = walk_velocity(
+                        velocity.0,
+                        controller_up(gravity.0, gravity.1),
+                        (*direction, sprint),
+                        (acceleration, damping, curve.copied().unwrap_or_default()),
+                        delta_secs,
+                    );
+                }
+                MovementAction::Jump => {
+                    let rising_gravity = RisingGravity::new(
+                        gravity.0,
+                        gravity_multipliers,
+                        fast_falling(entity, player),
+                    );
+                    if is_grounded || coyote_time.remaining > 0.0 {
+                        jump(
+                            (&mut velocity, half_step),
+                            jump_impulse,
+                            gravity,
+                            &rising_gravity,
+                            speed_bonus,
+                            grid_jump,
+                        );
+                        // Coyote jumps have left the slope behind
+                        if is_grounded {
+                            lean_off_slope(&mut velocity, gravity, slope);
+                        }
+                        commands.entity(entity).insert(JumpRising);
+                        coyote_time.remaining = 0.0;
+                        jump_buffer.remaining = 0.0;
+                    } else if jump_state.air_jumps_used
+                        < air_jumps.map_or(0, |air_jumps| air_jumps.0)
+                    {
+                        jump(
+                            (&mut velocity, half_step),
+                            jump_impulse,
+                            gravity,
+                            &rising_gravity,
+                            speed_bonus,
+                            grid_jump,
+                        );
+                        commands.entity(entity).insert(JumpRising);
+                        jump_state.air_jumps_used += 1;
+                    } else {
+                        jump_buffer.remaining = jump_buffer.duration;
+                    }
+                }
+                MovementAction::Dash(direction) => {
+                    if let Some(mut dash) = dash {
+                        let up = controller_up(gravity.0, gravity.1);
+                        dash.request(*direction, up, &mut velocity);
+                    }
+                }
+                MovementAction::Crouch
+                | MovementAction::DropThrough
+                | MovementAction::FastFall
+                | MovementAction::FlipGravity
+                | MovementAction::Climb
+                | MovementAction::JumpHeld => {}
+            }
+        }
+    }
+
+    // Buffered jumps fire on the first frame back on the ground
+    for (
+        (entity, player),
+        _,
+        _,
+        _,
+        jump_impulse,
+        gravity,
+        gravity_multipliers,
+        (mut velocity, half_step),
+        speed_bonus,
+        grid_jump,
+        _,
+        (mut coyote_time, mut jump_buffer, ..),
+        slope,
+        is_grounded,
+    ) in &mut controllers
+    {
+        if is_grounded && jump_buffer.remaining > 0.0 {
+            let rising_gravity =
+                RisingGravity::new(gravity.0, gravity_multipliers, fast_falling(entity, player));
+            jump(
+                (&mut velocity, half_step),
+                jump_impulse,
+                gravity,
+                &rising_gravity,
+                speed_bonus,
+                grid_jump,
+            );
+            lean_off_slope(&mut velocity, gravity, slope);
+            commands.entity(entity).insert(JumpRising);
+            coyote_time.remaining = 0.0;
+            jump_buffer.remaining = 0.0;
+        }
+    }
+}
+
+fn cut_jump(
+    mut commands: Commands,
+    mut action_events: EventReader<ControllerAction>,
+    mut controllers: Query<
+        (
+            Entity,
+            &PlayerIndex,
+            (&Gravity, Option<&UpDirection>),
+            (
+                Option<&JumpSnappiness>,
+                Option<(&ApexGravityMultiplier, &ApexThreshold)>,
+                &FastFallMultiplier,
+            ),
+            &mut LinearVelocity,
+            Option<&MinJumpHeight>,
+        ),
+        (With<JumpRising>, Without<Frozen>),
+    >,
+) {
+    let actions: Vec<ControllerAction> = action_events.read().copied().collect();
+    let sent = |action: MovementAction, entity: Entity, player: &PlayerIndex| {
+        actions
+            .iter()
+            .any(|event| event.action == action && event.targets(entity, player))
+    };
+
+    for (entity, player, (gravity, up_direction), gravity_multipliers, mut velocity, min_height) in
+        &mut controllers
+    {
+        let up = controller_up(gravity, up_direction);
+        let vertical_speed = velocity.dot(*up);
+
+        let Some(min_height) = min_height.filter(|_| vertical_speed > 0.0) else {
+            commands.entity(entity).remove::<JumpRising>();
+            continue;
+        };
+        if sent(MovementAction::JumpHeld, entity, player) {
+            continue;
+        }
+
+        // Released early, so the jump only goes on to reach the minimum height from here
+        let fast_falling = sent(MovementAction::FastFall, entity, player);
+        let min_speed = RisingGravity::new(gravity, gravity_multipliers, fast_falling)
+            .jump_speed_for_height(min_height.0);
+        if vertical_speed > min_speed {
+            velocity.0 -= (vertical_speed - min_speed) * up;
+        }
+        commands.entity(entity).remove::<JumpRising>();
+    }
+}
+
+fn jump_thrust(
+    time: Res<Time>,
+    mut action_events: EventReader<ControllerAction>,
+    mut controllers: Query<
+        (
+            Entity,
+            &PlayerIndex,
+            (&Gravity, Option<&UpDirection>),
+            &mut LinearVelocity,
+            &mut JumpThrust,
+            Has<Grounded>,
+        ),
+        (Without<Frozen>, Without<Hanging>),
+    >,
+) {
+    let delta_secs = time.delta_secs();
+    let holds: Vec<ControllerAction> = action_events
+        .read()
+        .filter(|event| event.action == MovementAction::JumpHeld)
+        .copied()
+        .collect();
+
+    for (entity, player, (gravity, up_direction), mut velocity, mut thrust, is_grounded) in
+        &mut controllers
+    {
+        if is_grounded {
+            thrust.remaining = thrust.fuel;
+            continue;
+        }
+        if thrust.remaining <= 0.0 || !holds.iter().any(|event| event.targets(entity, player)) {
+            continue;
+        }
+
+        // The last bit of fuel only thrusts for as long as it lasts
+        let burn = delta_secs.min(thrust.remaining);
+        thrust.remaining -= burn;
+        velocity.0 += controller_up(gravity, up_direction) * thrust.accel * burn;
+    }
+}
+
+fn update_jump_state(
+    mut controllers: Query<
+        (
+            &mut JumpState,
+            &CoyoteTime,
+            Option<&AirJumps>,
+            Has<Grounded>,
+            Has<Hanging>,
+        ),
+        Without<Frozen>,
+    >,
+) {
+    for (mut jump_state, coyote_time, air_jumps, is_grounded, is_hanging) in &mut controllers {
+        let max_air_jumps = air_jumps.map_or(0, |air_jumps| air_jumps.0);
+        let can_jump = !is_hanging
+            && (is_grounded
+                || coyote_time.remaining > 0.0
+                || jump_state.air_jumps_used < max_air_jumps);
+
+        jump_state.max_air_jumps = max_air_jumps;
+        jump_state.can_jump = can_jump;
+    }
+}
+
+fn ledge_hang(
+    mut commands: Commands,
+    spatial_query: Res<SpatialQueryPipeline>,
+    mut action_events: EventReader<ControllerAction>,
+    mut controllers: Query<
+        (
+            Entity,
+            &PlayerIndex,
+            &mut Position,
+            (&mut LinearVelocity, &mut GravityHalfStep),
+            &Rotation,
+            &Collider,
+            &CollisionLayerConfig,
+            (&Gravity, Option<&UpDirection>),
+            Option<&IgnoredColliders>,
+            Option<&Hanging>,
+            Has<Grounded>,
+        ),
+        (With<LedgeGrab>, Without<Frozen>),
+    >,
+) {
+    let actions: Vec<ControllerAction> = action_events.read().copied().collect();
+
+    for (
+        entity,
+        player,
+        mut position,
+        (mut velocity, mut half_step),
+        rotation,
+        collider,
+        collision_layers,
+        (gravity, up_direction),
+        ignored,
+        hanging,
+        is_grounded,
+    ) in &mut controllers
+    {
+        let mut walk_direction: Scalar = 0.0;
+        let mut climb = false;
+        let mut let_go = false;
+        for event in actions.iter().filter(|event| event.targets(entity, player)) {
+            match event.action {
+                MovementAction::Walk(direction) | MovementAction::Sprint(direction) => {
+                    walk_direction = direction;
+                }
+                MovementAction::Climb => climb = true,
+                MovementAction::Crouch => let_go = true,
+                _ => {}
+            }
+        }
+
+        let up = controller_up(gravity, up_direction);
+        let right = Vector::new(up.y, -up.x);
+        let half_size = collider.aabb(position.0, *rotation).size() * 0.5;
+        let half_height = half_size.dot(up.abs());
+        let half_width = half_size.dot(right.abs());
+
+        if let Some(hanging) = hanging {
+            velocity.0 = Vector::ZERO;
+            half_step.0 = Vector::ZERO;
+
+            if climb {
+                position.0 = hanging.ledge
+                    + right * hanging.direction * half_width
+                    + *up * (half_height + SKIN_WIDTH);
+                commands.entity(entity).remove::<Hanging>();
+            } else if let_go || walk_direction * hanging.direction < 0.0 {
+                commands.entity(entity).remove::<Hanging>();
+            }
+            continue;
+        }
+
+        if is_grounded || walk_direction == 0.0 || velocity.dot(*up) > 0.0 {
+            continue;
+        }
+
+        let direction = walk_direction.signum();
+        let Ok(forward) = Dir2::new(right * direction) else {
+            continue;
+        };
+        let filter = controller_filter(entity, collision_layers, ignored);
+        let head = position.0 + *up * half_height;
+        let reach = half_width + LEDGE_REACH;
+
+        // There has to be a wall just below the top of the controller but nothing level with it
+        if spatial_query
+            .cast_ray(head, forward, reach, true, &filter)
+            .is_some()
+        {
+            continue;
+        }
+        let Some(wall) = spatial_query.cast_ray(
+            head - *up * LEDGE_PROBE_DEPTH,
+            forward,
+            reach,
+            true,
+            &filter,
+        ) else {
+            continue;
+        };
+
+        // The top of the wall, found by casting down onto it from just past its face
+        let above_ledge = head + *forward * (wall.distance + LEDGE_REACH * 0.5);
+        let Some(top) = spatial_query.cast_ray(above_ledge, -up, LEDGE_PROBE_DEPTH, true, &filter)
+        else {
+            continue;
+        };
+        let ledge = above_ledge - *up * top.distance;
+
+        // Hang with the top of the controller level with the ledge. Gravity is skipped while
+        // hanging, so this step's half step would otherwise keep lifting the controller.
+        position.0 += *up * (ledge - head).dot(*up);
+        velocity.0 = Vector::ZERO;
+        half_step.0 = Vector::ZERO;
+        commands.entity(entity).insert(Hanging { ledge, direction });
+    }
+}
+
+fn apply_damping(
+    time: Res<Time>,
+    mut controllers: Query<
+        (
+            &MovementDamping,
+            &AirDamping,
+            &RidingPlatform,
+            (&Gravity, Option<&UpDirection>),
+            &mut LinearVelocity,
+            Has<Grounded>,
+        ),
+        Without<Frozen>,
+    >,
+    surfaces: Query<&SurfaceFriction>,
+) {
+    let delta_secs = time.delta_secs();
+
+    for (
+        ground_damping,
+        air_damping,
+        riding_platform,
+        (gravity, up_direction),
+        mut velocity,
+        is_grounded,
+    ) in &mut controllers
+    {
+        let friction = riding_platform
+            .0
+            .and_then(|ground| surfaces.get(ground).ok());
+        let damping = effective_damping((ground_damping.0, air_damping.0), friction, is_grounded);
+
+        let up = controller_up(gravity, up_direction);
+        velocity.0 = damping_step(velocity.0, damping, up, delta_secs);
+    }
+}
+
+fn update_air_time(
+    time: Res<Time>,
+    mut controllers: Query<(&mut AirTime, Has<Grounded>), Without<Frozen>>,
+) {
+    for (mut air_time, is_grounded) in &mut controllers {
+        if is_grounded {
+            air_time.set_if_neq(AirTime(0.0));
+        } else {
+            air_time.0 += time.delta_secs();
+        }
+    }
+}
+
+fn detect_landing(
+    mut commands: Commands,
+    mut land_events: EventWriter<LandEvent>,
+    mut controllers: Query<
+        (
+            Entity,
+            &Position,
+            (&Gravity, Option<&UpDirection>),
+            &mut FallApex,
+            Option<&HardLandingThreshold>,
+            Has<Grounded>,
+            Has<HardLanding>,
+        ),
+        Without<Frozen>,
+    >,
+) {
+    for (
+        entity,
+        position,
+        (gravity, up_direction),
+        mut fall_apex,
+        threshold,
+        is_grounded,
+        hard_landing,
+    ) in &mut controllers
+    {
+        // The marker only lasts for the frame of the landing
+        if hard_landing {
+            commands.entity(entity).remove::<HardLanding>();
+        }
+
+        let height = position.dot(*controller_up(gravity, up_direction));
+
+        if !is_grounded {
+            fall_apex.0 = Some(fall_apex.0.map_or(height, |apex| apex.max(height)));
+            continue;
+        }
+
+        let Some(apex) = fall_apex.0.take() else {
+            continue;
+        };
+
+        let fall_distance = (apex - height).max(0.0);
+        if threshold.is_some_and(|threshold| fall_distance > threshold.0) {
+            commands.entity(entity).insert(HardLanding);
+        }
+        land_events.write(LandEvent {
+            entity,
+            fall_distance,
+        });
+    }
+}
+
+fn update_squash_stretch(
+    mut land_events: EventReader<LandEvent>,
+    mut controllers: Query<
+        (
+            Entity,
+            &mut SquashStretch,
+            &LinearVelocity,
+            (&Gravity, Option<&UpDirection>),
+            &TerminalVelocity,
+        ),
+        Without<Frozen>,
+    >,
+) {
+    let landed: Vec<Entity> = land_events.read().map(|event| event.entity).collect();
+
+    for (entity, mut squash_stretch, velocity, (gravity, up_direction), terminal_velocity) in
+        &mut controllers
+    {
+        let length = if landed.contains(&entity) {
+            1.0 - LANDING_SQUASH
+        } else {
+            let vertical_speed = velocity.dot(*controller_up(gravity, up_direction)).abs();
+            1.0 + MAX_STRETCH * (vertical_speed / terminal_velocity.0.max(1.0)).min(1.0)
+        };
+
+        squash_stretch.set_if_neq(SquashStretch {
+            scale: Vec2::new(1.0 / length, length),
+        });
+    }
+}
+
+fn update_facing(
+    mut action_events: EventReader<ControllerAction>,
+    mut controllers: Query<(Entity, &PlayerIndex, &mut Facing), Without<Frozen>>,
+) {
+    let actions: Vec<ControllerAction> = action_events.read().copied().collect();
+
+    for (entity, player, mut controller_facing) in &mut controllers {
+        // The latest walk or sprint input for this controller decides which way it faces
+        let facing = actions
+            .iter()
+            .filter(|event| event.targets(entity, player))
+            .filter_map(|event| match event.action {
+                MovementAction::Walk(direction) | MovementAction::Sprint(direction)
+                    if direction < 0.0 =>
+                {
+                    Some(Facing::Left)
+                }
+                MovementAction::Walk(direction) | MovementAction::Sprint(direction)
+                    if direction > 0.0 =>
+                {
+                    Some(Facing::Right)
+                }
+                _ => None,
+            })
+            .last();
+
+        if let Some(facing) = facing {
+            controller_facing.set_if_neq(facing);
+        }
+    }
+}
+
+/// What a controller was asked to do during the latest step.
+#[derive(Clone, Copy, Default)]
+struct ControllerIntent {
+    walk_direction: Scalar,
+    sprinting: bool,
+    jumped: bool,
+}
+
+fn update_controller_state(
+    spatial_query: Res<SpatialQueryPipeline>,
+    mut action_events: EventReader<ControllerAction>,
+    mut state_changed_events: EventWriter<StateChanged>,
+    mut controllers: Query<
+        (
+            Entity,
+            &PlayerIndex,
+            &mut CharacterControllerState,
+            &LinearVelocity,
+            &Position,
+            &Rotation,
+            &Collider,
+            &CollisionLayerConfig,
+            (&Gravity, Option<&UpDirection>),
+            Option<&IgnoredColliders>,
+            Has<Grounded>,
+            Has<Hanging>,
+        ),
+        (With<CharacterController>, Without<Frozen>),
+    >,
+) {
+    let actions: Vec<ControllerAction> = action_events.read().copied().collect();
+    let intent_of = |entity: Entity, player: &PlayerIndex| {
+        let mut intent = ControllerIntent::default();
+        for event in actions.iter().filter(|event| event.targets(entity, player)) {
+            match event.action {
+                MovementAction::Walk(direction) => intent.walk_direction = direction,
+                MovementAction::Sprint(direction) => {
+                    intent.walk_direction = direction;
+                    intent.sprinting = true;
+                }
+                MovementAction::Jump => intent.jumped = true,
+                MovementAction::Dash(_)
+                | MovementAction::Crouch
+                | MovementAction::DropThrough
+                | MovementAction::FastFall
+                | MovementAction::FlipGravity
+                | MovementAction::Climb
+                | MovementAction::JumpHeld => (),
+            }
+        }
+        intent
+    };
+
+    for (
+        entity,
+        player,
+        mut state,
+        velocity,
+        position,
+        rotation,
+        collider,
+        collision_layers,
+        (gravity, up_direction),
+        ignored,
+        is_grounded,
+        is_hanging,
+    ) in &mut controllers
+    {
+        use CharacterControllerState as State;
+
+        let ControllerIntent {
+            walk_direction,
+            sprinting,
+            jumped,
+        } = intent_of(entity, player);
+
+        let up = controller_up(gravity, up_direction);
+        let rising = velocity.dot(*up) > 0.0;
+        // The ground caster keeps reporting hits for a few frames after takeoff, so a rising
+        // controller that was already jumping stays in the jumping state
+        let next_state = if is_hanging {
+            State::LedgeHang
+        } else if (jumped && is_grounded) || (rising && (!is_grounded || *state == State::Jumping))
+        {
+            State::Jumping
+        } else if is_grounded {
+            match (walk_direction != 0.0, sprinting) {
+                (false, _) => State::Idle,
+                (true, false) => State::Walking,
+                (true, true) => State::Sprinting,
+            }
+        } else if pushing_into_wall(
+            &spatial_query,
+            &controller_filter(entity, collision_layers, ignored),
+            collider,
+            position.0,
+            rotation.as_radians(),
+            up,
+            walk_direction,
+        ) {
+            State::WallSliding
+        } else {
+            State::Falling
+        };
+
+        // Only write on transitions so `Changed<CharacterControllerState>` stays meaningful
+        if let Some(previous_state) = state.replace_if_neq(next_state) {
+            state_changed_events.write(StateChanged {
+                entity,
+                from: previous_state,
+                to: next_state,
+            });
+        }
+    }
+}
+
+fn clamp_horizontal_speed(
+    mut controllers: Query<
+        (
+            &MaxHorizontalSpeed,
+            (&Gravity, Option<&UpDirection>),
+            &mut LinearVelocity,
+        ),
+        Without<Frozen>,
+    >,
+) {
+    for (max_speed, (gravity, up_direction), mut velocity) in &mut controllers {
+        let up = controller_up(gravity, up_direction);
+        velocity.0 = clamp_horizontal(velocity.0, up, max_speed.0);
+    }
+}
+
+fn detect_triggers(
+    spatial_query: Res<SpatialQueryPipeline>,
+    mut controllers: Query<
+        (
+            Entity,
+            &Position,
+            &Rotation,
+            &Collider,
+            &mut TriggerOverlaps,
+        ),
+        (With<CharacterController>, Without<Frozen>),
+    >,
+    sensors: Query<(), With<Sensor>>,
+    mut enter_events: EventWriter<TriggerEnter>,
+    mut exit_events: EventWriter<TriggerExit>,
+) {
+    for (controller, position, rotation, collider, mut overlaps) in &mut controllers {
+        // Triggers ignore the controller's collision layers, since trigger zones are usually on
+        // a layer the controller doesn't collide with
+        let current_overlaps: EntityHashSet = spatial_query
+            .shape_intersections(
+                collider,
+                position.0,
+                rotation.as_radians(),
+                &SpatialQueryFilter::from_excluded_entities([controller]),
+            )
+            .into_iter()
+            .filter(|&entity| sensors.contains(entity))
+            .collect();
+
+        if current_overlaps == overlaps.0 {
+            continue;
+        }
+
+        for &trigger in current_overlaps.difference(&overlaps.0) {
+            enter_events.write(TriggerEnter {
+                controller,
+                trigger,
+            });
+        }
+        for &trigger in overlaps.0.difference(&current_overlaps) {
+            exit_events.write(TriggerExit {
+                controller,
+                trigger,
+            });
+        }
+
+        overlaps.0 = current_overlaps;
+    }
+}
+
+/// Draws every controller's ground cast, ground normal and velocity with gizmos while present.
+///
+/// Insert or remove it at runtime to toggle the drawing. It complements avian's
+/// `PhysicsDebugPlugin`, which draws the colliders themselves.
+#[derive(Resource, Default)]
+pub struct ControllerDebug;
+
+fn debug_draw_controller(
+    mut gizmos: Gizmos,
+    controllers: Query<
+        (
+            &Position,
+            &Rotation,
+            &LinearVelocity,
+            &ShapeCaster,
+            &ShapeHits,
+        ),
+        With<CharacterController>,
+    >,
+) {
+    for (position, rotation, velocity, ground_caster, hits) in &controllers {
+        let cast_origin = position.0 + *rotation * ground_caster.origin;
+        let cast_direction = *rotation * *ground_caster.direction;
+        gizmos.line_2d(
+            cast_origin,
+            cast_origin + cast_direction * ground_caster.max_distance,
+            css::YELLOW,
+        );
+
+        for hit in hits.iter() {
+            gizmos.arrow_2d(
+                hit.point1,
+                hit.point1 + hit.normal1 * DEBUG_NORMAL_LENGTH,
+                css::LIME,
+            );
+        }
+
+        gizmos.arrow_2d(
+            position.0,
+            position.0 + velocity.0 * DEBUG_VELOCITY_SCALE,
+            css::RED,
+        );
+    }
+}
+
+/// Shows the controller's state, grounded flag and velocity as text above it, while the plugin
+/// was built with `debug` and [`ControllerDebug`] is present.
+#[derive(Component, Clone, Copy, Debug, Default)]
+pub struct ControllerDebugText;
+
+/// The text entity showing a [`ControllerDebugText`] controller's readout.
+#[derive(Component)]
+struct DebugTextLabel(Entity);
+
+fn spawn_debug_text(
+    mut commands: Commands,
+    controllers: Query<Entity, (With<ControllerDebugText>, Without<DebugTextLabel>)>,
+) {
+    for entity in &controllers {
+        // As a child the text follows the controller without a system of its own
+        let label = commands
+            .spawn((
+                Text2d::default(),
+                TextFont::from_font_size(12.0),
+                Transform::from_xyz(0.0, DEBUG_TEXT_OFFSET, 1.0),
+                ChildOf(entity),
+            ))
+            .id();
+        commands.entity(entity).insert(DebugTextLabel(label));
+    }
+}
+
+fn update_debug_text(
+    controllers: Query<(
+        &DebugTextLabel,
+        &CharacterControllerState,
+        &LinearVelocity,
+        Has<Grounded>,
+    )>,
+    mut labels: Query<&mut Text2d>,
+) {
+    for (label, state, velocity, is_grounded) in &controllers {
+        let Ok(mut text) = labels.get_mut(label.0) else {
+            continue;
+        };
+
+        let readout = format!(
+            "{state:?}\ngrounded: {is_grounded}\nvelocity: ({:.0}, {:.0})",
+            velocity.x, velocity.y
+        );
+        if text.0 != readout {
+            text.0 = readout;
+        }
+    }
+}
+
+fn log_state_transitions(
+    controllers: Query<(Entity, &CharacterControllerState), Changed<CharacterControllerState>>,
+) {
+    for (entity, state) in &controllers {
+        info!("Controller {entity} is now {state:?}");
+    }
+}
+
+fn pushing_into_wall(
+    spatial_query: &SpatialQueryPipeline,
+    filter: &SpatialQueryFilter,
+    collider: &Collider,
+    position: Vector,
+    rotation: Scalar,
+    up: Dir2,
+    walk_direction: Scalar,
+) -> bool {
+    // Walls are checked for along the direction perpendicular to up, which is +X for +Y
+    let right = Dir2::new(Vector::new(up.y, -up.x)).unwrap_or(Dir2::X);
+    let cast_direction = if walk_direction > 0.0 {
+        right
+    } else if walk_direction < 0.0 {
+        -right
+    } else {
+        return false;
+    };
+
+    spatial_query
+        .cast_shape(
+            collider,
+            position,
+            rotation,
+            cast_direction,
+            &ShapeCastConfig {
+                max_distance: WALL_CHECK_DISTANCE,
+                ..default()
+            },
+            filter,
+        )
+        .is_some()
+}
+
+/// Collide-and-slide tuning shared by every controller without a [`CollideAndSlideOverride`].
+///
+/// This is a resource so it can be tuned at runtime, e.g. from a debug UI.
+#[derive(Resource, Clone, Copy, Debug)]
+pub struct CollideAndSlideSettings {
+    pub bounces: usize,
+    pub skin_width: Scalar,
+    /// How many pieces each step's motion is split into, see [`CollideAndSlideConfig::substeps`].
+    pub substeps: usize,
+    /// Steps that would move the controller less than this distance leave it where it is, which
+    /// keeps it from creeping and jittering while it rests on a slope.
+    pub min_movement: Scalar,
+    /// See [`CollideAndSlideConfig::min_distance`].
+    pub min_slide_distance: Scalar,
+}
+
+impl Default for CollideAndSlideSettings {
+    fn default() -> Self {
+        Self {
+            bounces: MAX_BOUNCES,
+            skin_width: SKIN_WIDTH,
+            substeps: 1,
+            min_movement: MIN_MOVEMENT_DISTANCE,
+            min_slide_distance: MIN_SLIDE_DISTANCE,
+        }
+    }
+}
+
+/// Per-controller collide-and-slide tuning that takes precedence over [`CollideAndSlideSettings`].
+#[derive(Component, Clone, Copy, Debug)]
+pub struct CollideAndSlideOverride(pub CollideAndSlideSettings);
+
+/// Parameters for a single [`collide_and_slide`] query.
+pub struct CollideAndSlideConfig {
+    pub bounces: usize,
+    pub rotation: Scalar,
+    pub skin_width: Scalar,
+    /// Surfaces steeper than this, measured from `up`, are treated as walls the controller can
+    /// slide down but not climb.
+    pub max_slope_angle: Scalar,
+    pub up: Dir2,
+    pub filter: SpatialQueryFilter,
+    /// How many equal pieces the motion is split into, each resolved with its own casts.
+    /// More substeps cost more queries but keep very fast controllers from slipping past thin
+    /// colliders between bounces.
+    pub substeps: usize,
+    /// Sliding stops early once the motion left over after a hit is shorter than this.
+    pub min_distance: Scalar,
+}
+
+impl Default for CollideAndSlideConfig {
+    fn default() -> Self {
+        CollideAndSlideConfig {
+            bounces: MAX_BOUNCES,
+            rotation: 0.0,
+            skin_width: SKIN_WIDTH,
+            max_slope_angle: PI * 0.45,
+            up: Dir2::Y,
+            // This filter collides with everything
+            filter: SpatialQueryFilter::from_excluded_entities([]),
+            substeps: 1,
+            min_distance: MIN_SLIDE_DISTANCE,
+        }
+    }
+}
+
+/// The outcome of a [`collide_and_slide`] query.
+struct SlideResult {
+    /// The displacement that can be applied without entering any geometry.
+    displacement: Vector,
+    /// Every surface hit along the way, in order.
+    hits: Vec<ShapeHitData>,
+}
+
+/// The colliders a [`collide_and_slide`] of `collider` by `motion` from `position` could hit,
+/// which are those whose bounds overlap the bounds swept by the whole motion.
+fn slide_candidates(
+    spatial_query: &SpatialQueryPipeline,
+    collider: &Collider,
+    position: Vector,
+    motion: Vector,
+    config: &CollideAndSlideConfig,
+) -> EntityHashSet {
+    // Every bounce of every substep can push the controller out of an overlap by up to a skin
+    // width on top of the motion itself
+    let pushes = config.substeps.max(1) * (config.bounces + 1);
+    let margin = Vector::splat(config.skin_width * pushes as Scalar);
+    let start = collider.aabb(position, config.rotation);
+    let end = collider.aabb(position + motion, config.rotation);
+
+    let mut nearby: EntityHashSet = spatial_query
+        .aabb_intersections_with_aabb(ColliderAabb {
+            min: start.min.min(end.min) - margin,
+            max: start.max.max(end.max) + margin,
+        })
+        .into_iter()
+        .collect();
+    nearby.retain(|entity| !config.filter.excluded_entities.contains(entity));
+    nearby
+}
+
+/// Moves `collider` by `motion` from `position`, sliding along every surface it hits.
+///
+/// Surfaces for which `pass_through` returns true for the direction of a cast are ignored by it.
+fn collide_and_slide(
+    spatial_query: &SpatialQueryPipeline,
+    collider: &Collider,
+    position: Vector,
+    motion: Vector,
+    config: &CollideAndSlideConfig,
+    pass_through: &dyn Fn(Entity, Dir2) -> bool,
+) -> SlideResult {
+    let substeps = config.substeps.max(1);
+    let step = motion / substeps as Scalar;
+
+    let nearby = slide_candidates(spatial_query, collider, position, motion, config);
+    if nearby.is_empty() {
+        // Same as casting into empty space, where steps too short to bother with don't move
+        let moves = config.bounces > 0 && step.length() >= config.min_distance;
+        return SlideResult {
+            displacement: if moves { motion } else { Vector::ZERO },
+            hits: Vec::new(),
+        };
+    }
+    // The casts skip narrow phase tests against everything else in the level
+    let pass_through = |entity: Entity, direction: Dir2| {
+        !nearby.contains(&entity) || pass_through(entity, direction)
+    };
+
+    let mut displacement = Vector::ZERO;
+    let mut hits: Vec<ShapeHitData> = Vec::new();
+
+    for _ in 0..substeps {
+        // Later substeps don't push back into surfaces that earlier ones already hit
+        let mut step_motion = step;
+        for hit in &hits {
+            step_motion -= hit.normal1 * step_motion.dot(hit.normal1).min(0.0);
+        }
+
+        let result = slide_step(
+            spatial_query,
+            collider,
+            position + displacement,
+            step_motion,
+            config,
+            &pass_through,
+        );
+        displacement += result.displacement;
+        hits.extend(result.hits);
+    }
+
+    SlideResult { displacement, hits }
+}
+
+/// A single substep of [`collide_and_slide`].
+fn slide_step(
+    spatial_query: &SpatialQueryPipeline,
+    collider: &Collider,
+    position: Vector,
+    motion: Vector,
+    config: &CollideAndSlideConfig,
+    pass_through: &dyn Fn(Entity, Dir2) -> bool,
+) -> SlideResult {
+    let mut position = position;
+    let mut remaining_motion = motion;
+    let mut displacement = Vector::ZERO;
+    let mut hits = Vec::new();
+
+    for _ in 0..config.bounces {
+        let distance = remaining_motion.length();
+        if distance < config.min_distance {
+            break;
+        }
+        let Ok(cast_direction) = Dir2::new(remaining_motion) else {
+            break;
+        };
+
+        let Some(hit) = spatial_query.cast_shape_predicate(
+            collider,
+            position,
+            config.rotation,
+            cast_direction,
+            &ShapeCastConfig {
+                max_distance: distance + config.skin_width,
+                ..default()
+            },
+            &config.filter,
+            &|entity| !pass_through(entity, cast_direction),
+        ) else {
+            displacement += remaining_motion;
+            break;
+        };
+
+        if hit.distance <= 0.0 {
+            // Already overlapping, so step out along the surface normal and cast again from there
+            let push_out = hit.normal1 * config.skin_width;
+            displacement += push_out;
+            position += push_out;
+            remaining_motion -= hit.normal1 * remaining_motion.dot(hit.normal1).min(0.0);
+            hits.push(hit);
+            continue;
+        }
+
+        // Stop where the gap to the surface, measured along its normal, is `skin_width`, so the
+        // next cast doesn't start inside it. Shallow hits need to back off further along the cast.
+        let approach = -cast_direction.dot(hit.normal1);
+        let back_off = (config.skin_width / approach.max(Scalar::EPSILON)).min(hit.distance);
+        let snap_to_surface = cast_direction * (hit.distance - back_off);
+        displacement += snap_to_surface;
+        position += snap_to_surface;
+
+        // Whatever motion is left over is projected onto the surface we hit
+        let leftover = remaining_motion - snap_to_surface;
+        remaining_motion = leftover - hit.normal1 * leftover.dot(hit.normal1);
+
+        // Slopes that are too steep would let the projection walk the controller up them, so
+        // they're treated like vertical walls instead. Sliding down them is still allowed.
+        let up = config.up;
+        if is_wall(hit.normal1, up, config.max_slope_angle)
+            && hit.normal1.dot(*up) > 0.0
+            && remaining_motion.dot(*up) > 0.0
+        {
+            let wall_normal = hit.normal1.reject_from_normalized(*up).normalize_or_zero();
+            remaining_motion = leftover - wall_normal * leftover.dot(wall_normal).min(0.0);
+        }
+
+        // Rising into an angled ceiling slides along it. Motion that wasn't going down is never
+        // turned downwards by one though, since that would fight the upward motion of the other
+        // pass and make the controller jitter under overhangs.
+        if ContactKind::classify(hit.normal1, up, config.max_slope_angle) == ContactKind::Ceiling
+            && leftover.dot(*up) >= 0.0
+            && remaining_motion.dot(*up) < 0.0
+        {
+            remaining_motion = Vector::ZERO;
+        }
+
+        hits.push(hit);
+    }
+
+    SlideResult { displacement, hits }
+}
+
+/// The smallest sideways nudge, up to `width`, that clears the way for `motion` after it clipped
+/// the corner of `ceiling`, or `None` if the controller hit the ceiling squarely.
+fn corner_nudge(
+    spatial_query: &SpatialQueryPipeline,
+    collider: &Collider,
+    (position, motion): (Vector, Vector),
+    ceiling: &ShapeHitData,
+    width: Scalar,
+    config: &CollideAndSlideConfig,
+    pass_through: &dyn Fn(Entity, Dir2) -> bool,
+) -> Option<Vector> {
+    // A corner pushes back at an angle, which also tells us which way is away from it. Sideways
+    // is perpendicular to up, so flipped and rotated controllers are nudged along their own right.
+    let right = Vector::new(config.up.y, -config.up.x);
+    let across = ceiling.normal1.dot(right);
+    if ceiling.normal1.dot(*config.up) >= 0.0 || across.abs() < Scalar::EPSILON {
+        return None;
+    }
+    let side = Dir2::new(right * across.signum()).ok()?;
+    let cast = |origin: Vector, direction: Dir2, distance: Scalar| {
+        spatial_query.cast_shape_predicate(
+            collider,
+            origin,
+            config.rotation,
+            direction,
+            &ShapeCastConfig {
+                max_distance: distance + config.skin_width,
+                ..default()
+            },
+            &config.filter,
+            &|entity| !pass_through(entity, direction),
+        )
+    };
+
+    // Never nudge into a wall
+    let room = cast(position, side, width)
+        .map_or(width, |hit| (hit.distance - config.skin_width).max(0.0))
+        .min(width);
+    if room <= 0.0 {
+        return None;
+    }
+    let up = Dir2::new(motion).ok()?;
+
+    (1..=CORNER_CORRECTION_STEPS)
+        .map(|step| side * room * step as Scalar / CORNER_CORRECTION_STEPS as Scalar)
+        .find(|&nudge| cast(position + nudge, up, motion.length()).is_none())
+}
+
+/// The shape the ground caster casts for a controller with the given `collider`.
+fn ground_caster_shape(collider: &Collider, scale: GroundCasterScale) -> Collider {
+    let mut shape = collider.clone();
+    shape.set_scale(Vector::ONE * scale.0, 10);
+    shape
+}
+
+/// Whether a surface with the given `normal` is too steep to stand on but isn't a ceiling.
+fn is_wall(normal: Vector, up: Dir2, max_slope_angle: Scalar) -> bool {
+    ContactKind::classify(normal, up, max_slope_angle) == ContactKind::Wall
+}
+
+/// Makes overlapping controllers push each other apart while present, instead of standing
+/// inside each other.
+#[derive(Resource, Clone, Copy, Debug)]
+pub struct ControllerPushConfig {
+    /// The fraction of the overlap resolved every step. Lower values make the push softer.
+    pub stiffness: Scalar,
+}
+
+impl Default for ControllerPushConfig {
+    fn default() -> Self {
+        Self { stiffness: 1.0 }
+    }
+}
+
+/// Separates every pair of overlapping controllers along their contact normal, moving each one
+/// by half of the resolved penetration.
+fn push_controllers(
+    config: Res<ControllerPushConfig>,
+    collisions: Collisions,
+    mut controllers: Query<
+        &mut Position,
+        (
+            With<CharacterController>,
+            Without<Frozen>,
+            Without<SkipCollision>,
+        ),
+    >,
+) {
+    for contacts in collisions.iter() {
+        let Ok([mut position1, mut position2]) =
+            controllers.get_many_mut([contacts.collider1, contacts.collider2])
+        else {
+            continue;
+        };
+
+        for manifold in &contacts.manifolds {
+            let penetration = manifold
+                .points
+                .iter()
+                .map(|point| point.penetration)
+                .fold(0.0, Scalar::max);
+
+            // Manifold normals point from the first collider towards the second
+            let push = manifold.normal * penetration * config.stiffness * 0.5;
+            position1.0 -= push;
+            position2.0 += push;
+        }
+    }
+}
+
+/// Reports the velocity the collision response settled on in [`Speed`].
+fn update_speed(
+    mut controllers: Query<
+        (
+            &mut Speed,
+            &LinearVelocity,
+            (&Gravity, Option<&UpDirection>),
+        ),
+        Without<Frozen>,
+    >,
+) {
+    for (mut speed, velocity, (gravity, up_direction)) in &mut controllers {
+        let up = controller_up(gravity, up_direction);
+        speed.set_if_neq(Speed {
+            horizontal: velocity.reject_from_normalized(*up).length(),
+            total: velocity.length(),
+            direction: Dir2::new(velocity.0).ok(),
+        });
+    }
+}
+
+fn clear_skip_collision(
+    mut commands: Commands,
+    mut controllers: Query<
+        (Entity, &mut LinearVelocity, &mut GravityHalfStep),
+        With<SkipCollision>,
+    >,
+) {
+    for (entity, mut velocity, mut half_step) in &mut controllers {
+        // Without a collision response nothing would stop the solver from moving the controller
+        // into whatever it was placed against
+        velocity.0 = Vector::ZERO;
+        half_step.0 = Vector::ZERO;
+        commands.entity(entity).remove::<SkipCollision>();
+    }
+}
+
+/// Pushes controllers out of static geometry they overlap, like a platform spawned on top of
+/// them or a wall they were teleported into, along the minimum translation vector.
+fn overlap_recovery(
+    collisions: Collisions,
+    colliders: Query<
+        (&ColliderOf, Option<&CollisionLayers>),
+        (Without<Sensor>, Without<OneWayPlatform>),
+    >,
+    bodies: Query<&RigidBody>,
+    mut controllers: Query<
+        (&mut Position, &CollisionLayerConfig),
+        (
+            With<CharacterController>,
+            Without<Frozen>,
+            Without<SkipCollision>,
+        ),
+    >,
+) {
+    for contacts in collisions.iter() {
+        // Manifold normals point from the first collider towards the second
+        let (controller, other, sign) = if controllers.contains(contacts.collider1) {
+            (contacts.collider1, contacts.collider2, -1.0)
+        } else if controllers.contains(contacts.collider2) {
+            (contacts.collider2, contacts.collider1, 1.0)
+        } else {
+            continue;
+        };
+
+        let Ok((&ColliderOf { body }, layers)) = colliders.get(other) else {
+            continue;
+        };
+        if !bodies.get(body).is_ok_and(RigidBody::is_static) {
+            continue;
+        }
+
+        let Ok((mut position, collision_layers)) = controllers.get_mut(controller) else {
+            continue;
+        };
+        let memberships = layers.copied().unwrap_or_default().memberships;
+        if collision_layers.mask & memberships == LayerMask::NONE {
+            continue;
+        }
+
+        for manifold in &contacts.manifolds {
+            let penetration = manifold
+                .points
+                .iter()
+                .map(|point| point.penetration)
+                .fold(0.0, Scalar::max);
+            position.0 += manifold.normal * sign * penetration;
+        }
+    }
+}
+
+fn kinematic_collision_response(
+    time: Res<Time>,
+    spatial_query: Res<SpatialQueryPipeline>,
+    settings: Res<CollideAndSlideSettings>,
+    mut controllers: Query<
+        (
+            Entity,
+            &mut LinearVelocity,
+            &mut Position,
+            &Rotation,
+            &Collider,
+            &CollisionLayerConfig,
+            (&Gravity, Option<&UpDirection>, &mut GravityHalfStep),
+            &MaxSlopeAngle,
+            &GroundSurfaceVelocity,
+            &RidingPlatform,
+            &mut ContactState,
+            Option<&IgnoredColliders>,
+            Option<&CollideAndSlideOverride>,
+            (
+                Option<&WallBounce>,
+                Option<&PushDynamicBodies>,
+                Option<&CornerCorrectionWidth>,
+            ),
+        ),
+        (
+            With<CharacterController>,
+            Without<Frozen>,
+            Without<SkipCollision>,
+        ),
+    >,
+    (bounce_pads, one_way_platforms): (Query<&BouncePad>, Query<(), With<OneWayPlatform>>),
+    collider_bodies: Query<&ColliderOf>,
+    mut bodies: Query<(&RigidBody, &mut LinearVelocity), Without<CharacterController>>,
+) {
+    let delta_secs = time.delta_secs();
+    if delta_secs == 0.0 {
+        return;
+    }
+
+    for (
+        entity,
+        mut velocity,
+        mut position,
+        rotation,
+        collider,
+        collision_layers,
+        (gravity, up_direction, mut half_step),
+        max_slope_angle,
+        ground_surface_velocity,
+        riding_platform,
+        mut contact_state,
+        ignored,
+        settings_override,
+        (wall_bounce, push_bodies, corner_correction),
+    ) in &mut controllers
+    {
+        // A bad external write shouldn't crash the game or poison the slide, so the velocity is
+        // reset instead
+        if !velocity.is_finite() {
+            warn!("Controller {entity} had a non-finite velocity, resetting it to 0");
+            velocity.0 = Vector::ZERO;
+            half_step.0 = Vector::ZERO;
+            continue;
+        }
+
+        let settings = settings_override.map_or(*settings, |settings_override| settings_override.0);
+        let config = CollideAndSlideConfig {
+            bounces: settings.bounces,
+            rotation: rotation.as_radians(),
+            skin_width: settings.skin_width,
+            max_slope_angle: max_slope_angle.0,
+            up: controller_up(gravity, up_direction),
+            filter: controller_filter(entity, collision_layers, ignored),
+            substeps: settings.substeps,
+            min_distance: settings.min_slide_distance,
+        };
+
+        // One-way platforms only block motion going down onto them, and never while the
+        // controller is still partway through them
+        let up = config.up;
+        let inside_one_way: Vec<Entity> = spatial_query
+            .shape_intersections(collider, position.0, config.rotation, &config.filter)
+            .into_iter()
+            .filter(|&platform| one_way_platforms.contains(platform))
+            .collect();
+        let pass_through = |platform: Entity, direction: Dir2| {
+            one_way_platforms.contains(platform)
+                && (direction.dot(*up) >= 0.0 || inside_one_way.contains(&platform))
+        };
+
+        // The motion is split across and along up, so sideways gravity slides the same way
+        let right = Vector::new(up.y, -up.x);
+        let motion = velocity.0 - half_step.0;
+        let horizontal = collide_and_slide(
+            &spatial_query,
+            collider,
+            position.0,
+            motion.dot(right) * right * delta_secs,
+            &config,
+            &pass_through,
+        );
+        // Starting where the horizontal pass ended keeps inside corners from being counted twice
+        let vertical_motion = motion.dot(*up) * up * delta_secs;
+        let mut vertical = collide_and_slide(
+            &spatial_query,
+            collider,
+            position.0 + horizontal.displacement,
+            vertical_motion,
+            &config,
+            &pass_through,
+        );
+
+        let nudge = corner_correction
+            .filter(|_| vertical_motion.dot(*up) > 0.0)
+            .zip(vertical.hits.first())
+            .and_then(|(width, ceiling)| {
+                corner_nudge(
+                    &spatial_query,
+                    collider,
+                    (position.0 + horizontal.displacement, vertical_motion),
+                    ceiling,
+                    width.0,
+                    &config,
+                    &pass_through,
+                )
+            });
+        if let Some(nudge) = nudge {
+            // The nudge moves the controller directly, so it doesn't carry on sideways afterwards
+            position.0 += nudge;
+            vertical = collide_and_slide(
+                &spatial_query,
+                collider,
+                position.0 + horizontal.displacement,
+                vertical_motion,
+                &config,
+                &pass_through,
+            );
+        }
+
+        let incoming_velocity = velocity.0;
+        let mut displacement = horizontal.displacement + vertical.displacement;
+        velocity.0 = displacement / delta_secs;
+        // Blocked motion keeps the velocity it ended up with
+        let kept = |hits: &[ShapeHitData], axis: Vector| {
+            if hits.is_empty() {
+                half_step.0.dot(axis) * axis
+            } else {
+                Vector::ZERO
+            }
+        };
+        half_step.0 = kept(&horizontal.hits, right) + kept(&vertical.hits, *up);
+
+        if displacement.length() < settings.min_movement {
+            displacement = Vector::ZERO;
+            velocity.0 = Vector::ZERO;
+            half_step.0 = Vector::ZERO;
+        }
+
+        // The ground moves the controller directly rather than through its velocity, so the
+        // carried speed isn't damped away or kept after stepping off. A moving platform will
+        // itself have moved by the end of the step, so it's left out of this cast.
+        if ground_surface_velocity.0 != Vector::ZERO {
+            let carried_config = CollideAndSlideConfig {
+                filter: config
+                    .filter
+                    .clone()
+                    .with_excluded_entities(riding_platform.0),
+                ..config
+            };
+            let carried = collide_and_slide(
+                &spatial_query,
+                collider,
+                position.0 + displacement,
+                ground_surface_velocity.0 * delta_secs,
+                &carried_config,
+                &pass_through,
+            );
+            position.0 += carried.displacement;
+        }
+
+        contact_state.0.clear();
+        contact_state
+            .0
+            .extend(
+                horizontal
+                    .hits
+                    .iter()
+                    .chain(&vertical.hits)
+                    .map(|hit| ControllerContact {
+                        entity: hit.entity,
+                        normal: hit.normal1,
+                        kind: ContactKind::classify(hit.normal1, up, max_slope_angle.0),
+                    }),
+            );
+
+        let landed_pad = vertical
+            .hits
+            .iter()
+            .filter(|hit| {
+                ContactKind::classify(hit.normal1, up, max_slope_angle.0) == ContactKind::Floor
+            })
+            .find_map(|hit| bounce_pads.get(hit.entity).ok());
+
+        if let Some(pad) = landed_pad.filter(|_| incoming_velocity.dot(*up) < 0.0) {
+            // Replaces the landing speed that the slide just removed
+            velocity.0 += (pad.impulse - velocity.dot(*up)) * up;
+        }
+
+        if push_bodies.is_some_and(|push_bodies| push_bodies.0) {
+            for hit in horizontal.hits.iter().chain(&vertical.hits) {
+                let Ok(&ColliderOf { body }) = collider_bodies.get(hit.entity) else {
+                    continue;
+                };
+                let Ok((rigid_body, mut body_velocity)) = bodies.get_mut(body) else {
+                    continue;
+                };
+                // Bodies below the controller are stood on rather than pushed
+                let direction = -hit.normal1;
+                if !rigid_body.is_dynamic() || direction.dot(*up) < 0.0 {
+                    continue;
+                }
+
+                // The body is sped up to the controller's speed into it, the slide has already
+                // stopped the controller itself
+                let push_speed = incoming_velocity.dot(direction) - body_velocity.dot(direction);
+                if push_speed > 0.0 {
+                    body_velocity.0 += direction * push_speed;
+                }
+            }
+        }
+
+        let Some(wall_bounce) = wall_bounce else {
+            continue;
+        };
+        let bounced_velocity = horizontal
+            .hits
+            .iter()
+            .find(|hit| is_wall(hit.normal1, up, max_slope_angle.0))
+            .and_then(|hit| wall_bounce.bounce(incoming_velocity, hit.normal1));
+
+        if let Some(bounced_velocity) = bounced_velocity {
+            // The controller is already touching the wall, so moving away from it is always safe.
+            // Only the speed across up bounces, the speed along it was settled by the slide.
+            velocity.0 += (bounced_velocity - velocity.0).dot(right) * right;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,2984 @@
+use std::time::Duration;
+
+use bevy::{ecs::system::RunSystemOnce, time::TimeUpdateStrategy};
+
+use super::*;
+
+/// The width and height of the test controllers' capsule.
+const CONTROLLER_SIZE: Vector = Vector::new(30.0, 60.0);
+/// The strength of the test controllers' gravity.
+const GRAVITY: Scalar = 1000.0;
+/// The height of a test controller's center while it stands on [`spawn_floor`].
+const STANDING_HEIGHT: Scalar = CONTROLLER_SIZE.y / 2.0 + SKIN_WIDTH;
+
+/// A headless app that runs the controller and avian at 60hz, one fixed step per update.
+fn test_app() -> App {
+    test_app_with(CharacterControllerPlugin::new(60.0))
+}
+
+/// Like [`test_app`], with the given `plugin`, stepping at its physics rate or 60hz if it has none.
+fn test_app_with(plugin: CharacterControllerPlugin) -> App {
+    let PhysicsRate(hz) = plugin.physics_rate.unwrap_or(PhysicsRate(60.0));
+    let mut app = App::new();
+    app.add_plugins((
+        MinimalPlugins,
+        TransformPlugin,
+        AssetPlugin::default(),
+        bevy::scene::ScenePlugin,
+        PhysicsPlugins::default(),
+        plugin,
+    ))
+    .init_resource::<Assets<Mesh>>()
+    .insert_resource(TimeUpdateStrategy::ManualDuration(Duration::from_secs_f64(
+        1.0 / hz,
+    )));
+    app.finish();
+    app.cleanup();
+
+    // The first update only starts the clock, so every later one runs exactly one fixed step
+    app.update();
+    app
+}
+
+fn step(app: &mut App, steps: usize) {
+    for _ in 0..steps {
+        app.update();
+    }
+}
+
+/// An upright capsule controller with [`CONTROLLER_SIZE`] and downward [`GRAVITY`].
+fn controller_bundle() -> CharacterControllerBundle {
+    let radius = CONTROLLER_SIZE.x / 2.0;
+    CharacterControllerBundle::new(
+        Collider::capsule(radius, CONTROLLER_SIZE.y - 2.0 * radius),
+        Vector::NEG_Y * GRAVITY,
+    )
+}
+
+fn spawn_controller(app: &mut App, bundle: impl Bundle, position: Vector) -> Entity {
+    app.world_mut()
+        .spawn((bundle, Transform::from_translation(position.extend(0.0))))
+        .id()
+}
+
+fn insert(app: &mut App, entity: Entity, bundle: impl Bundle) {
+    app.world_mut().entity_mut(entity).insert(bundle);
+}
+
+/// Spawns a static box of `size` centered on `position`.
+fn spawn_box(app: &mut App, position: Vector, size: Vector) -> Entity {
+    app.world_mut()
+        .spawn((
+            RigidBody::Static,
+            Collider::rectangle(size.x, size.y),
+            Transform::from_translation(position.extend(0.0)),
+        ))
+        .id()
+}
+
+/// Spawns a wide floor whose top is at `y = 0`.
+fn spawn_floor(app: &mut App) -> Entity {
+    spawn_box(app, Vector::new(0.0, -10.0), Vector::new(4000.0, 20.0))
+}
+
+/// Spawns a static slope tilted `angle` degrees counterclockwise, whose top surface runs 400
+/// units from the origin towards `side`, -1.0 for left and 1.0 for right.
+fn spawn_slope(app: &mut App, angle: Scalar, side: Scalar) -> Entity {
+    let rotation = Rot2::degrees(angle);
+    app.world_mut()
+        .spawn((
+            RigidBody::Static,
+            Collider::rectangle(400.0, 20.0),
+            Transform::from_translation((rotation * Vector::new(200.0 * side, -10.0)).extend(0.0))
+                .with_rotation(Quat::from_rotation_z(rotation.as_radians())),
+        ))
+        .id()
+}
+
+fn send(app: &mut App, controller: Entity, action: MovementAction) {
+    app.world_mut()
+        .send_event(ControllerAction::controller(controller, action));
+}
+
+/// Every action sent to controllers since [`record_actions`] was added.
+#[derive(Resource, Default)]
+struct SentActions(Vec<MovementAction>);
+
+/// Records the actions the controller's input sends into [`SentActions`].
+fn record_actions(app: &mut App) {
+    fn collect(mut action_events: EventReader<ControllerAction>, mut sent: ResMut<SentActions>) {
+        sent.0
+            .extend(action_events.read().map(|event| event.action));
+    }
+
+    app.init_resource::<SentActions>()
+        .add_systems(FixedUpdate, collect.after(ControllerSet::Input));
+}
+
+/// Holds `keys` for two frames, since keyboard input is only sent on the fixed step after the
+/// frame it was read on, and returns the actions that were sent.
+fn press(app: &mut App, keys: &[KeyCode]) -> Vec<MovementAction> {
+    app.world_mut().resource_mut::<SentActions>().0.clear();
+    let mut input = app.world_mut().resource_mut::<ButtonInput<KeyCode>>();
+    input.reset_all();
+    for &key in keys {
+        input.press(key);
+    }
+
+    app.update();
+    // Without the input plugin nothing else ends the presses' first frame
+    app.world_mut()
+        .resource_mut::<ButtonInput<KeyCode>>()
+        .clear();
+    app.update();
+
+    app.world().resource::<SentActions>().0.clone()
+}
+
+fn component<T: Component>(app: &App, entity: Entity) -> &T {
+    app.world().get::<T>(entity).unwrap()
+}
+
+fn velocity(app: &App, controller: Entity) -> Vector {
+    component::<LinearVelocity>(app, controller).0
+}
+
+fn position(app: &App, controller: Entity) -> Vector {
+    component::<Position>(app, controller).0
+}
+
+fn is_grounded(app: &App, controller: Entity) -> bool {
+    app.world().entity(controller).contains::<Grounded>()
+}
+
+fn state(app: &App, controller: Entity) -> CharacterControllerState {
+    *component::<CharacterControllerState>(app, controller)
+}
+
+#[test]
+fn state_goes_from_idle_through_a_jump_and_back() {
+    use CharacterControllerState as State;
+
+    let mut app = test_app();
+    spawn_floor(&mut app);
+    let controller = spawn_controller(&mut app, controller_bundle(), Vector::Y * STANDING_HEIGHT);
+    step(&mut app, 5);
+
+    let mut states = vec![state(&app, controller)];
+    for frame in 0..120 {
+        if frame < 10 {
+            send(&mut app, controller, MovementAction::Walk(1.0));
+        } else if frame == 10 {
+            send(&mut app, controller, MovementAction::Jump);
+        }
+        app.update();
+
+        let state = state(&app, controller);
+        if states.last() != Some(&state) {
+            states.push(state);
+        }
+    }
+
+    assert_eq!(
+        states,
+        [
+            State::Idle,
+            State::Walking,
+            State::Jumping,
+            State::Falling,
+            State::Idle
+        ]
+    );
+}
+
+#[test]
+fn dash_pressed_during_cooldown_fires_when_the_cooldown_ends() {
+    let mut app = test_app();
+    let controller = spawn_controller(
+        &mut app,
+        (controller_bundle(), Dash::new(600.0, 0.5, 0.2)),
+        Vector::ZERO,
+    );
+
+    send(&mut app, controller, MovementAction::Dash(1.0));
+    app.update();
+    assert!(velocity(&app, controller).x > 0.0);
+
+    // 0.1 seconds of cooldown are left, which is within the buffer window
+    step(&mut app, 24);
+    send(&mut app, controller, MovementAction::Dash(-1.0));
+    app.update();
+    assert!(velocity(&app, controller).x > 0.0);
+
+    let mut frames = 0;
+    while velocity(&app, controller).x > 0.0 {
+        app.update();
+        frames += 1;
+        assert!(frames <= 6, "the buffered dash never fired");
+    }
+    assert!((5..=6).contains(&frames));
+    assert!(velocity(&app, controller).x < -500.0);
+}
+
+#[test]
+fn gravity_saturates_at_terminal_velocity() {
+    let mut app = test_app();
+    let controller = spawn_controller(
+        &mut app,
+        controller_bundle().with_terminal_velocity(300.0),
+        Vector::ZERO,
+    );
+
+    let mut fastest_fall: Scalar = 0.0;
+    for _ in 0..120 {
+        app.update();
+        fastest_fall = fastest_fall.max(-velocity(&app, controller).y);
+    }
+
+    assert!(fastest_fall <= 300.0 + 1e-3);
+    assert!((velocity(&app, controller).y + 300.0).abs() < 1e-3);
+}
+
+#[test]
+fn collide_and_slide_settings_apply_to_controllers_without_an_override() {
+    let mut app = test_app();
+    let tuned = spawn_controller(&mut app, controller_bundle(), Vector::ZERO);
+    let overridden = spawn_controller(
+        &mut app,
+        (
+            controller_bundle(),
+            CollideAndSlideOverride(CollideAndSlideSettings::default()),
+        ),
+        Vector::new(200.0, 0.0),
+    );
+    for controller in [tuned, overridden] {
+        insert(&mut app, controller, LinearVelocity(Vector::X * 100.0));
+    }
+
+    // Without any bounces a controller can't move at all
+    app.world_mut()
+        .resource_mut::<CollideAndSlideSettings>()
+        .bounces = 0;
+    app.update();
+
+    assert_eq!(position(&app, tuned), Vector::ZERO);
+    assert!(position(&app, overridden).x > 200.0);
+}
+
+#[test]
+fn airborne_walking_accelerates_differently_from_grounded_walking() {
+    let mut app = test_app();
+    spawn_floor(&mut app);
+    let grounded = spawn_controller(&mut app, controller_bundle(), Vector::Y * STANDING_HEIGHT);
+    let airborne = spawn_controller(&mut app, controller_bundle(), Vector::new(200.0, 500.0));
+    step(&mut app, 5);
+    assert!(is_grounded(&app, grounded));
+    assert!(!is_grounded(&app, airborne));
+
+    for controller in [grounded, airborne] {
+        send(&mut app, controller, MovementAction::Walk(1.0));
+    }
+    app.update();
+
+    let grounded_speed = velocity(&app, grounded).x;
+    let airborne_speed = velocity(&app, airborne).x;
+    assert!(grounded_speed > 0.0 && airborne_speed > 0.0);
+    assert!((grounded_speed - airborne_speed).abs() > 1.0);
+}
+
+#[test]
+fn rotated_controller_stops_where_its_rotated_shape_meets_a_wall() {
+    let mut app = test_app();
+    let wall_face = 140.0;
+    spawn_box(&mut app, Vector::new(150.0, 0.0), Vector::new(20.0, 400.0));
+
+    let rotation = Quat::from_rotation_z(15.0_f32.to_radians());
+    let controller = app
+        .world_mut()
+        .spawn((
+            // No gravity, so the controller only moves sideways
+            CharacterControllerBundle::new(Collider::rectangle(10.0, 60.0), Vector::ZERO),
+            Transform::from_xyz(100.0, 0.0, 0.0).with_rotation(rotation),
+        ))
+        .id();
+
+    for _ in 0..30 {
+        insert(&mut app, controller, LinearVelocity(Vector::X * 300.0));
+        app.update();
+    }
+
+    // Tilted by 15 degrees, the box reaches 5 * cos(15) + 30 * sin(15) ~= 12.6 to its right,
+    // instead of the 5 it would if the casts ignored its rotation
+    let gap = wall_face - position(&app, controller).x;
+    assert!((12.0..14.0).contains(&gap), "stopped {gap} from the wall");
+}
+
+#[test]
+fn grid_jump_apex_is_a_multiple_of_the_unit() {
+    let grid_jump = GridJump { unit: 16.0 };
+
+    for jump_impulse in [50.0, 230.0, 400.0, 615.0] {
+        let speed = grid_jump.snapped_impulse(jump_impulse, GRAVITY);
+        let apex = speed * speed / (2.0 * GRAVITY);
+        let units = apex / grid_jump.unit;
+
+        assert!(units >= 1.0 - 1e-3);
+        assert!((units - units.round()).abs() < 1e-3, "apex of {apex}");
+    }
+}
+
+#[test]
+fn sideways_gravity_grounds_on_the_surface_it_pulls_towards() {
+    let mut app = test_app();
+    spawn_box(&mut app, Vector::new(100.0, 0.0), Vector::new(20.0, 400.0));
+    let mut bundle = controller_bundle();
+    bundle.gravity = Gravity(Vector::X * GRAVITY);
+    let controller = spawn_controller(&mut app, bundle, Vector::new(74.5, 0.0));
+
+    step(&mut app, 5);
+
+    assert!(is_grounded(&app, controller));
+}
+
+#[test]
+fn sideways_gravity_walks_across_it_and_falls_along_it() {
+    let mut app = test_app();
+    // A wall whose left face, at x = 90, is the floor for gravity pulling towards +X
+    spawn_box(&mut app, Vector::new(100.0, 0.0), Vector::new(20.0, 4000.0));
+    let [walker, faller] = [0.0, -1000.0].map(|y| {
+        let bundle = controller_bundle().with_gravity_direction(Dir2::X);
+        let controller = spawn_controller(&mut app, bundle, Vector::new(-100.0, y));
+        insert(&mut app, controller, MaxHorizontalSpeed(50.0));
+        controller
+    });
+
+    for _ in 0..10 {
+        send(&mut app, walker, MovementAction::Walk(1.0));
+        app.update();
+    }
+
+    // Up is -X, so the controller's right is +Y. Walking and its speed limit run along that,
+    // while the fall along +X is the same as for a controller that isn't walking.
+    let [walking, falling] = [walker, faller].map(|controller| velocity(&app, controller));
+    assert!((walking.y - 50.0).abs() < 1e-3, "walked at {walking}");
+    assert_eq!(falling.y, 0.0);
+    assert!(falling.x > 50.0);
+    assert!((walking.x - falling.x).abs() < 1e-3);
+
+    step(&mut app, 60);
+    let start = position(&app, walker);
+    for _ in 0..10 {
+        send(&mut app, walker, MovementAction::Walk(1.0));
+        app.update();
+    }
+
+    assert!(is_grounded(&app, walker));
+    assert!(position(&app, walker).y > start.y + 1.0);
+    for controller in [walker, faller] {
+        assert!((position(&app, controller).x - 74.5).abs() < 1.0);
+    }
+}
+
+#[test]
+fn plugin_settings_are_applied() {
+    let default_tuning = ControllerTuning {
+        acceleration: 3000.0,
+        ..default()
+    };
+    let mut app = test_app_with(CharacterControllerPlugin {
+        default_tuning,
+        debug: true,
+        ..CharacterControllerPlugin::new(60.0)
+    });
+    let tuned = spawn_controller(&mut app, controller_bundle(), Vector::ZERO);
+    let custom = spawn_controller(
+        &mut app,
+        controller_bundle().with_movement(500.0, 0.9, 300.0, PI * 0.25),
+        Vector::new(200.0, 0.0),
+    );
+
+    app.update();
+
+    let debug_logging = app
+        .get_schedule(FixedUpdate)
+        .unwrap()
+        .systems()
+        .unwrap()
+        .any(|(_, system)| system.name().contains("log_state_transitions"));
+    assert!(debug_logging);
+    assert_eq!(component::<MovementAcceleration>(&app, tuned).0, 3000.0);
+    assert_eq!(component::<MovementAcceleration>(&app, custom).0, 500.0);
+}
+
+#[test]
+fn spawn_helper_returns_the_controller() {
+    let mut app = test_app();
+
+    let controller = app
+        .world_mut()
+        .run_system_once(|mut commands: Commands| {
+            spawn_character_controller(
+                &mut commands,
+                Collider::capsule(15.0, 30.0),
+                Vector::new(10.0, 20.0),
+                Vector::NEG_Y * GRAVITY,
+            )
+        })
+        .unwrap();
+
+    assert!(
+        app.world()
+            .entity(controller)
+            .contains::<CharacterController>()
+    );
+    assert_eq!(
+        component::<Transform>(&app, controller).translation,
+        Vec3::new(10.0, 20.0, 0.0)
+    );
+}
+
+#[test]
+fn wall_bounce_reflects_only_fast_shallow_hits() {
+    let wall_bounce = WallBounce {
+        min_speed: 200.0,
+        restitution: 1.0,
+    };
+    // A wall to the right of the controller
+    let normal = Vector::NEG_X;
+
+    // 20 degrees off the wall's surface, moving up and into it
+    let angle = 20.0_f32.to_radians();
+    let shallow = Vector::new(angle.sin(), angle.cos()) * 400.0;
+    let bounced = wall_bounce.bounce(shallow, normal).unwrap();
+    assert!((bounced.x + shallow.x).abs() < 1e-3);
+    assert!((bounced.y - shallow.y).abs() < 1e-3);
+
+    let slow = shallow.normalize() * 100.0;
+    assert_eq!(wall_bounce.bounce(slow, normal), None);
+
+    let head_on = Vector::X * 400.0;
+    assert_eq!(wall_bounce.bounce(head_on, normal), None);
+}
+
+#[test]
+fn user_systems_can_be_ordered_around_controller_sets() {
+    #[derive(Resource, Default)]
+    struct SeenSpeeds {
+        before_movement: Scalar,
+        after_movement: Scalar,
+    }
+
+    fn before_movement(
+        mut seen: ResMut<SeenSpeeds>,
+        controllers: Query<&LinearVelocity, With<CharacterController>>,
+    ) {
+        seen.before_movement = controllers.single().unwrap().x;
+    }
+
+    fn after_movement(
+        mut seen: ResMut<SeenSpeeds>,
+        controllers: Query<&LinearVelocity, With<CharacterController>>,
+    ) {
+        seen.after_movement = controllers.single().unwrap().x;
+    }
+
+    let mut app = test_app();
+    app.init_resource::<SeenSpeeds>().add_systems(
+        FixedUpdate,
+        (
+            before_movement.before(ControllerSet::Movement),
+            after_movement.after(ControllerSet::Movement),
+        ),
+    );
+    let controller = spawn_controller(&mut app, controller_bundle(), Vector::ZERO);
+
+    send(&mut app, controller, MovementAction::Walk(1.0));
+    app.update();
+
+    let seen = app.world().resource::<SeenSpeeds>();
+    assert_eq!(seen.before_movement, 0.0);
+    assert!(seen.after_movement > 0.0);
+}
+
+#[test]
+fn down_alone_crouches_while_down_and_jump_drop_through() {
+    let mut app = test_app();
+    app.init_resource::<ButtonInput<KeyCode>>();
+    app.world_mut().resource_mut::<InputBindings>().drop_through = DropThroughTrigger::DownAndJump;
+    record_actions(&mut app);
+
+    let down = press(&mut app, &[KeyCode::KeyS]);
+    assert!(down.contains(&MovementAction::Crouch));
+    assert!(!down.contains(&MovementAction::DropThrough));
+
+    let down_and_jump = press(&mut app, &[KeyCode::KeyS, KeyCode::Space]);
+    assert!(down_and_jump.contains(&MovementAction::DropThrough));
+    assert!(!down_and_jump.contains(&MovementAction::Jump));
+}
+
+fn gravity_tuning() -> GravityTuning {
+    GravityTuning {
+        gravity: Vector::NEG_Y * GRAVITY,
+        up: Dir2::Y,
+        enabled: true,
+        terminal_velocity: 300.0,
+        fall_multiplier: 1.0,
+        fast_fall_multiplier: 1.0,
+        snappiness: None,
+        apex: None,
+    }
+}
+
+fn velocity_tuning() -> VelocityTuning {
+    VelocityTuning {
+        gravity: gravity_tuning(),
+        acceleration: 1200.0,
+        air_acceleration: 600.0,
+        damping: 0.9,
+        air_damping: 1.0,
+        curve: MovementCurve::Linear,
+        jump_impulse: 400.0,
+        max_horizontal_speed: None,
+    }
+}
+
+fn airborne(velocity: Vector) -> VelocityState {
+    VelocityState {
+        velocity,
+        half_step: Vector::ZERO,
+        grounded: false,
+        friction: None,
+    }
+}
+
+fn grounded(velocity: Vector) -> VelocityState {
+    VelocityState {
+        grounded: true,
+        ..airborne(velocity)
+    }
+}
+
+#[test]
+fn integrate_velocity_applies_gravity() {
+    let next = integrate_velocity(
+        airborne(Vector::ZERO),
+        VelocityInput::default(),
+        0.1,
+        &velocity_tuning(),
+    );
+
+    assert!((next.velocity - Vector::new(0.0, -100.0)).length() < 1e-3);
+    // Half of the step's gravity is held back, see `GravityHalfStep`
+    assert!((next.half_step - Vector::new(0.0, -50.0)).length() < 1e-3);
+}
+
+#[test]
+fn integrate_velocity_damps_horizontal_motion_on_the_ground() {
+    let next = integrate_velocity(
+        grounded(Vector::new(100.0, 0.0)),
+        VelocityInput::default(),
+        1.0 / 60.0,
+        &velocity_tuning(),
+    );
+
+    assert!((next.velocity.x - 90.0).abs() < 1e-3);
+}
+
+#[test]
+fn integrate_velocity_clamps_falls_to_the_terminal_velocity() {
+    let next = integrate_velocity(
+        airborne(Vector::new(0.0, -290.0)),
+        VelocityInput::default(),
+        0.1,
+        &velocity_tuning(),
+    );
+
+    assert_eq!(next.velocity.y, -300.0);
+}
+
+#[test]
+fn integrate_velocity_only_jumps_from_the_ground() {
+    let jump = VelocityInput {
+        jump: true,
+        ..default()
+    };
+
+    let tuning = velocity_tuning();
+    let jumped = integrate_velocity(grounded(Vector::ZERO), jump, 0.1, &tuning);
+    // The step's gravity is applied on top of the jump
+    assert!((jumped.velocity.y - (400.0 - GRAVITY * 0.1)).abs() < 1e-3);
+    let in_air = integrate_velocity(airborne(Vector::ZERO), jump, 0.1, &tuning);
+    assert!(in_air.velocity.y < 0.0);
+}
+
+#[test]
+fn integrate_velocity_limits_horizontal_speed_and_follows_surface_friction() {
+    let tuning = VelocityTuning {
+        max_horizontal_speed: Some(50.0),
+        ..velocity_tuning()
+    };
+    let walk = VelocityInput {
+        direction: 1.0,
+        ..default()
+    };
+    let next = integrate_velocity(grounded(Vector::X * 45.0), walk, 0.1, &tuning);
+    assert!((next.velocity.x - 50.0).abs() < 1e-3);
+
+    // Friction takes away twice the usual speed, so 0.8 is kept per 1/60th of a second
+    let icy = VelocityState {
+        friction: Some(SurfaceFriction(2.0)),
+        ..grounded(Vector::X * 100.0)
+    };
+    let next = integrate_velocity(
+        icy,
+        VelocityInput::default(),
+        1.0 / 60.0,
+        &velocity_tuning(),
+    );
+    assert!((next.velocity.x - 80.0).abs() < 1e-3);
+}
+
+#[test]
+fn integrate_velocity_matches_the_controller_systems() {
+    let mut app = test_app();
+    let controller = spawn_controller(
+        &mut app,
+        (
+            controller_bundle(),
+            JumpSnappiness(0.7),
+            MovementCurve::EaseOut,
+            MaxHorizontalSpeed(150.0),
+        ),
+        Vector::Y * 1000.0,
+    );
+    app.update();
+
+    let tuning = VelocityTuning {
+        gravity: GravityTuning {
+            terminal_velocity: component::<TerminalVelocity>(&app, controller).0,
+            fall_multiplier: component::<FallGravityMultiplier>(&app, controller).0,
+            snappiness: Some(0.7),
+            ..gravity_tuning()
+        },
+        acceleration: component::<MovementAcceleration>(&app, controller).0,
+        air_acceleration: component::<AirAcceleration>(&app, controller).0,
+        damping: component::<MovementDamping>(&app, controller).0,
+        air_damping: component::<AirDamping>(&app, controller).0,
+        curve: MovementCurve::EaseOut,
+        jump_impulse: component::<JumpImpulse>(&app, controller).0,
+        max_horizontal_speed: Some(150.0),
+    };
+    // The controller moves by its velocity minus the half step, which gravity then puts back
+    let half_step = component::<GravityHalfStep>(&app, controller).0;
+    let mut state = airborne(velocity(&app, controller) + half_step);
+    state.half_step = half_step;
+
+    let walk = VelocityInput {
+        direction: 1.0,
+        ..default()
+    };
+    for _ in 0..30 {
+        send(&mut app, controller, MovementAction::Walk(1.0));
+        app.update();
+        state = integrate_velocity(state, walk, 1.0 / 60.0, &tuning);
+
+        let half_step = component::<GravityHalfStep>(&app, controller).0;
+        let simulated = velocity(&app, controller) + half_step;
+        assert!(
+            (simulated - state.velocity).length() < 1e-2,
+            "systems moved at {simulated}, integrate_velocity at {}",
+            state.velocity
+        );
+    }
+}
+
+#[test]
+fn snappier_jumps_peak_sooner_and_fall_faster() {
+    let mut app = test_app();
+    let mut spawn_jumping = |snappiness: Scalar, x: Scalar| {
+        let controller = spawn_controller(
+            &mut app,
+            (controller_bundle(), JumpSnappiness(snappiness)),
+            Vector::new(x, 0.0),
+        );
+        insert(&mut app, controller, LinearVelocity(Vector::Y * 400.0));
+        controller
+    };
+    let floaty = spawn_jumping(0.1, 0.0);
+    let snappy = spawn_jumping(0.9, 200.0);
+
+    let mut vertical_speeds = [Vec::new(), Vec::new()];
+    for _ in 0..120 {
+        app.update();
+        for (speeds, controller) in vertical_speeds.iter_mut().zip([floaty, snappy]) {
+            speeds.push(velocity(&app, controller).y);
+        }
+    }
+
+    // The step each controller stopped rising on, and how fast it fell 4 steps later, before
+    // either reaches its terminal velocity
+    let [floaty_arc, snappy_arc] = vertical_speeds.map(|speeds| {
+        let peak = speeds.iter().position(|&speed| speed <= 0.0).unwrap();
+        (peak, speeds[peak + 4])
+    });
+    assert!(snappy_arc.0 < floaty_arc.0);
+    assert!(snappy_arc.1 < floaty_arc.1);
+}
+
+#[test]
+fn wind_added_in_modify_velocity_makes_the_controller_drift() {
+    const WIND: Scalar = 200.0;
+
+    fn blow(
+        time: Res<Time>,
+        mut controllers: Query<&mut LinearVelocity, With<CharacterController>>,
+    ) {
+        for mut velocity in &mut controllers {
+            velocity.x += WIND * time.delta_secs();
+        }
+    }
+
+    let mut app = test_app();
+    app.add_systems(FixedUpdate, blow.in_set(ControllerSet::ModifyVelocity));
+    let mut bundle = controller_bundle();
+    bundle.gravity = Gravity(Vector::ZERO);
+    let controller = spawn_controller(&mut app, bundle, Vector::ZERO);
+
+    step(&mut app, 30);
+
+    assert!(velocity(&app, controller).x > 0.0);
+    assert!(position(&app, controller).x > 0.0);
+    assert_eq!(position(&app, controller).y, 0.0);
+}
+
+#[test]
+fn headless_app_steps_a_controller() {
+    let mut app = test_app();
+    spawn_floor(&mut app);
+    let controller = spawn_controller(&mut app, controller_bundle(), Vector::Y * 100.0);
+
+    step(&mut app, 60);
+
+    assert!(position(&app, controller).y < 100.0);
+}
+
+#[test]
+fn gravity_is_applied_once_per_step_however_many_actions_arrive() {
+    fn fall_after(actions: usize) -> Scalar {
+        let mut app = test_app();
+        let controller = spawn_controller(&mut app, controller_bundle(), Vector::ZERO);
+        for _ in 0..actions {
+            send(&mut app, controller, MovementAction::Walk(1.0));
+        }
+        app.update();
+        velocity(&app, controller).y
+    }
+
+    let fall = fall_after(1);
+    assert!(fall < 0.0);
+    assert_eq!(fall_after(3), fall);
+}
+
+#[test]
+fn collision_mask_ignores_other_layers_but_stops_on_its_own() {
+    const TERRAIN: LayerMask = LayerMask(0b01);
+    const TRIGGERS: LayerMask = LayerMask(0b10);
+
+    let mut app = test_app();
+    let floor = spawn_floor(&mut app);
+    insert(
+        &mut app,
+        floor,
+        CollisionLayers::new(TERRAIN, LayerMask::ALL),
+    );
+    let trigger_zone = spawn_box(&mut app, Vector::Y * 100.0, Vector::new(200.0, 20.0));
+    insert(
+        &mut app,
+        trigger_zone,
+        CollisionLayers::new(TRIGGERS, LayerMask::ALL),
+    );
+    let controller = spawn_controller(
+        &mut app,
+        controller_bundle().with_collision_mask(TERRAIN),
+        Vector::Y * 200.0,
+    );
+
+    step(&mut app, 120);
+
+    assert!(is_grounded(&app, controller));
+    assert!((position(&app, controller).y - STANDING_HEIGHT).abs() < 1.0);
+}
+
+fn drain_events<E: Event>(app: &mut App) -> Vec<E> {
+    app.world_mut()
+        .resource_mut::<Events<E>>()
+        .drain()
+        .collect()
+}
+
+#[test]
+fn trigger_events_fire_on_first_overlap_and_on_separation() {
+    let mut app = test_app();
+    let trigger = spawn_box(&mut app, Vector::ZERO, Vector::new(100.0, 100.0));
+    insert(&mut app, trigger, Sensor);
+    let mut bundle = controller_bundle();
+    bundle.gravity = Gravity(Vector::ZERO);
+    let controller = spawn_controller(&mut app, bundle, Vector::ZERO);
+
+    // The spatial query pipeline only picks up the new colliders on the first physics step
+    step(&mut app, 2);
+    assert_eq!(
+        drain_events::<TriggerEnter>(&mut app),
+        [TriggerEnter {
+            controller,
+            trigger
+        }]
+    );
+
+    step(&mut app, 2);
+    assert!(drain_events::<TriggerEnter>(&mut app).is_empty());
+    assert!(drain_events::<TriggerExit>(&mut app).is_empty());
+
+    let away = Vector::X * 500.0;
+    insert(
+        &mut app,
+        controller,
+        (
+            Position(away),
+            Transform::from_translation(away.extend(0.0)),
+        ),
+    );
+    step(&mut app, 2);
+    assert_eq!(
+        drain_events::<TriggerExit>(&mut app),
+        [TriggerExit {
+            controller,
+            trigger
+        }]
+    );
+}
+
+#[test]
+fn respawn_moves_the_controller_and_clears_its_motion() {
+    let mut app = test_app();
+    let controller = spawn_controller(
+        &mut app,
+        (controller_bundle(), Dash::new(600.0, 0.5, 0.2)),
+        Vector::ZERO,
+    );
+    insert(
+        &mut app,
+        controller,
+        (
+            CoyoteTime {
+                duration: 0.1,
+                remaining: 0.1,
+            },
+            JumpBuffer {
+                duration: 0.1,
+                remaining: 0.1,
+            },
+            JumpState {
+                air_jumps_used: 1,
+                ..default()
+            },
+        ),
+    );
+    send(&mut app, controller, MovementAction::Dash(1.0));
+    app.update();
+    assert!(component::<Dash>(&app, controller).cooldown_remaining > 0.0);
+
+    let target = Vector::new(500.0, 300.0);
+    app.world_mut().send_event(RespawnEvent {
+        entity: controller,
+        position: target,
+    });
+    app.update();
+
+    // The respawn runs before this step's gravity, so only one step of falling is left
+    assert!(position(&app, controller).distance(target) < 1.0);
+    assert_eq!(velocity(&app, controller).x, 0.0);
+    assert!(velocity(&app, controller).y > -GRAVITY / 30.0);
+    assert_eq!(component::<Dash>(&app, controller).cooldown_remaining, 0.0);
+    assert_eq!(component::<CoyoteTime>(&app, controller).remaining, 0.0);
+    assert_eq!(component::<JumpBuffer>(&app, controller).remaining, 0.0);
+    assert_eq!(component::<JumpState>(&app, controller).air_jumps_used, 0);
+}
+
+#[test]
+fn nan_velocity_is_reset_instead_of_panicking() {
+    let mut app = test_app();
+    let controller = spawn_controller(&mut app, controller_bundle(), Vector::ZERO);
+    insert(
+        &mut app,
+        controller,
+        LinearVelocity(Vector::new(0.0, Scalar::NAN)),
+    );
+
+    app.update();
+
+    assert_eq!(velocity(&app, controller), Vector::ZERO);
+    assert!(position(&app, controller).is_finite());
+}
+
+#[test]
+fn grounded_and_airborne_controllers_decelerate_at_different_rates() {
+    let mut app = test_app();
+    spawn_floor(&mut app);
+    let bundle = || controller_bundle().with_air_damping(0.99);
+    let grounded = spawn_controller(&mut app, bundle(), Vector::Y * STANDING_HEIGHT);
+    let airborne = spawn_controller(&mut app, bundle(), Vector::new(200.0, 500.0));
+    step(&mut app, 5);
+    assert!(is_grounded(&app, grounded));
+    assert!(!is_grounded(&app, airborne));
+
+    for controller in [grounded, airborne] {
+        insert(&mut app, controller, LinearVelocity(Vector::X * 200.0));
+    }
+    step(&mut app, 10);
+
+    assert!(velocity(&app, grounded).x < velocity(&app, airborne).x);
+    assert!(velocity(&app, airborne).x < 200.0);
+}
+
+#[test]
+fn damping_decays_the_same_in_one_step_as_in_many() {
+    let velocity = Vector::new(300.0, 50.0);
+
+    let one_step = damping_step(velocity, 0.9, Dir2::Y, 0.5);
+    let many_steps = (0..120).fold(velocity, |velocity, _| {
+        damping_step(velocity, 0.9, Dir2::Y, 0.5 / 120.0)
+    });
+
+    assert!((one_step.x - many_steps.x).abs() < 1e-2);
+    assert_eq!(one_step.y, velocity.y);
+}
+
+#[test]
+fn damping_only_slows_motion_across_up() {
+    // With up along -X, falling is towards +X and the controller's right is +Y
+    let damped = damping_step(Vector::new(300.0, 100.0), 0.9, Dir2::NEG_X, 1.0 / 60.0);
+
+    assert_eq!(damped.x, 300.0);
+    assert!((damped.y - 90.0).abs() < 1e-3);
+}
+
+#[test]
+fn frame_rate_does_not_change_where_the_controller_ends_up() {
+    // 1.5 seconds of frames at the given frame length, which both divide exactly
+    let simulate = |frame_millis: u64| {
+        let mut app = test_app();
+        app.insert_resource(TimeUpdateStrategy::ManualDuration(Duration::from_millis(
+            frame_millis,
+        )));
+        spawn_floor(&mut app);
+        let controller = spawn_controller(&mut app, controller_bundle(), Vector::Y * 200.0);
+        insert(&mut app, controller, LinearVelocity(Vector::X * 100.0));
+
+        step(&mut app, (1500 / frame_millis) as usize);
+        position(&app, controller)
+    };
+
+    let at_40hz = simulate(25);
+    let at_200hz = simulate(5);
+
+    assert!(at_40hz.distance(at_200hz) < 1e-3);
+}
+
+#[test]
+fn gravity_direction_and_scale_combine() {
+    let bundle = controller_bundle()
+        .with_gravity_direction(Dir2::X)
+        .with_gravity_scale(0.5);
+
+    assert_eq!(bundle.gravity.0, Dir2::X * GRAVITY * 0.5);
+}
+
+#[test]
+fn gravity_is_weaker_at_the_apex_than_while_falling_fast() {
+    let mut app = test_app();
+    let mut spawn_moving = |vertical_speed: Scalar, x: Scalar| {
+        let controller = spawn_controller(
+            &mut app,
+            (
+                // Only the apex multiplier should make a difference
+                controller_bundle().with_fall_gravity_multiplier(1.0),
+                ApexGravityMultiplier(0.5),
+                ApexThreshold(50.0),
+            ),
+            Vector::new(x, 0.0),
+        );
+        insert(
+            &mut app,
+            controller,
+            LinearVelocity(Vector::Y * vertical_speed),
+        );
+        controller
+    };
+    let at_apex = spawn_moving(-5.0, 0.0);
+    let falling = spawn_moving(-200.0, 200.0);
+
+    app.update();
+
+    let apex_gravity = -5.0 - velocity(&app, at_apex).y;
+    let falling_gravity = -200.0 - velocity(&app, falling).y;
+    assert!(apex_gravity > 0.0);
+    assert!(apex_gravity < falling_gravity);
+}
+
+#[test]
+fn falls_gain_speed_faster_than_rises_lose_it() {
+    let tuning = VelocityTuning {
+        gravity: GravityTuning {
+            fall_multiplier: 1.8,
+            ..gravity_tuning()
+        },
+        ..velocity_tuning()
+    };
+    let step = |vertical_speed: Scalar| {
+        let velocity = Vector::Y * vertical_speed;
+        let next = integrate_velocity(airborne(velocity), VelocityInput::default(), 0.01, &tuning);
+        (next.velocity.y - vertical_speed).abs()
+    };
+
+    let rising = step(100.0);
+    let falling = step(-100.0);
+
+    assert!((rising - GRAVITY * 0.01).abs() < 1e-3);
+    assert!((falling - 1.8 * GRAVITY * 0.01).abs() < 1e-3);
+}
+
+#[test]
+fn fall_gravity_multiplier_still_stops_at_terminal_velocity() {
+    let mut app = test_app();
+    let controller = spawn_controller(
+        &mut app,
+        controller_bundle()
+            .with_fall_gravity_multiplier(3.0)
+            .with_terminal_velocity(300.0),
+        Vector::ZERO,
+    );
+
+    let mut fastest_fall: Scalar = 0.0;
+    for _ in 0..60 {
+        app.update();
+        fastest_fall = fastest_fall.max(-velocity(&app, controller).y);
+    }
+
+    assert!(fastest_fall <= 300.0 + 1e-3);
+    assert!((velocity(&app, controller).y + 300.0).abs() < 1e-3);
+}
+
+/// Systems that only use the public API, the way a game's animation or UI code would.
+mod external {
+    use bevy::prelude::*;
+
+    use crate::character_controller::Grounded;
+
+    #[derive(Resource, Default)]
+    pub struct GroundedControllers(pub Vec<Entity>);
+
+    pub fn find_grounded(
+        mut found: ResMut<GroundedControllers>,
+        controllers: Query<Entity, With<Grounded>>,
+    ) {
+        found.0 = controllers.iter().collect();
+    }
+}
+
+#[test]
+fn external_systems_can_query_grounded_controllers() {
+    use external::{GroundedControllers, find_grounded};
+
+    let mut app = test_app();
+    app.init_resource::<GroundedControllers>()
+        .add_systems(FixedUpdate, find_grounded.after(ControllerSet::Gravity));
+    spawn_floor(&mut app);
+    let controller = spawn_controller(&mut app, controller_bundle(), Vector::Y * 100.0);
+
+    step(&mut app, 2);
+    assert!(app.world().resource::<GroundedControllers>().0.is_empty());
+
+    step(&mut app, 120);
+    let grounded = &app.world().resource::<GroundedControllers>().0;
+    assert_eq!(grounded, &[controller]);
+}
+
+#[test]
+fn controller_started_inside_a_wall_ends_up_at_the_skin_boundary() {
+    let mut app = test_app();
+    // The wall's left face is at x = 90
+    spawn_box(&mut app, Vector::X * 100.0, Vector::new(20.0, 200.0));
+    let half_width = CONTROLLER_SIZE.x / 2.0;
+    let mut bundle = controller_bundle();
+    bundle.gravity = Gravity(Vector::ZERO);
+    let controller = spawn_controller(&mut app, bundle, Vector::X * (90.0 - half_width + 1.0));
+    insert(&mut app, controller, LinearVelocity(Vector::X * 60.0));
+
+    step(&mut app, 10);
+
+    let gap = 90.0 - (position(&app, controller).x + half_width);
+    assert!(gap >= 0.0);
+    assert!(gap <= SKIN_WIDTH + 0.1);
+}
+
+#[test]
+fn controller_spawned_inside_a_block_is_pushed_out_in_one_step() {
+    let mut app = test_app();
+    // The block's top is at y = 20, 40 above the controller's bottom
+    spawn_box(&mut app, Vector::ZERO, Vector::new(100.0, 40.0));
+    let mut bundle = controller_bundle();
+    bundle.gravity = Gravity(Vector::ZERO);
+    let controller = spawn_controller(&mut app, bundle, Vector::Y * 10.0);
+
+    app.update();
+
+    let bottom = position(&app, controller).y - CONTROLLER_SIZE.y / 2.0;
+    assert!(bottom >= 20.0 - 0.1);
+}
+
+#[test]
+fn oversized_horizontal_velocity_is_clamped_to_the_max_speed() {
+    let mut app = test_app();
+    let controller = spawn_controller(
+        &mut app,
+        (controller_bundle(), MaxHorizontalSpeed(300.0)),
+        Vector::ZERO,
+    );
+    insert(&mut app, controller, LinearVelocity(Vector::X * 2000.0));
+
+    app.update();
+
+    assert!((velocity(&app, controller).x - 300.0).abs() < 1e-3);
+}
+
+#[test]
+fn conveyor_belt_carries_a_standing_controller() {
+    let mut app = test_app();
+    let belt = spawn_floor(&mut app);
+    insert(&mut app, belt, SurfaceVelocity(Vector::X * 50.0));
+    let controller = spawn_controller(&mut app, controller_bundle(), Vector::Y * STANDING_HEIGHT);
+
+    step(&mut app, 60);
+
+    assert!(is_grounded(&app, controller));
+    // Close to a second of riding at 50 units per second
+    assert!(position(&app, controller).x > 40.0);
+}
+
+#[test]
+fn ignored_child_colliders_dont_block_the_controller() {
+    let mut app = test_app();
+    let plain = spawn_controller(&mut app, controller_bundle(), Vector::ZERO);
+    let with_hitbox = spawn_controller(&mut app, controller_bundle(), Vector::Y * 200.0);
+    let hitbox = app
+        .world_mut()
+        .spawn((
+            Collider::circle(10.0),
+            Sensor,
+            Transform::from_xyz(20.0, 0.0, 0.0),
+            ChildOf(with_hitbox),
+        ))
+        .id();
+    insert(&mut app, with_hitbox, IgnoredColliders(vec![hitbox]));
+    for controller in [plain, with_hitbox] {
+        insert(&mut app, controller, LinearVelocity(Vector::X * 100.0));
+    }
+
+    step(&mut app, 30);
+
+    assert!(position(&app, plain).x > 0.0);
+    assert!((position(&app, with_hitbox).x - position(&app, plain).x).abs() < 1e-3);
+}
+
+#[test]
+fn debug_drawing_follows_the_controller_debug_resource() {
+    let mut app = test_app();
+    // The parts of the gizmo plugin that don't need a renderer
+    app.init_asset::<GizmoAsset>()
+        .init_gizmo_group::<DefaultGizmoConfigGroup>();
+    spawn_controller(&mut app, controller_bundle(), Vector::ZERO);
+
+    step(&mut app, 2);
+    assert!(app.world().resource::<Assets<GizmoAsset>>().is_empty());
+
+    app.init_resource::<ControllerDebug>();
+    step(&mut app, 2);
+    assert!(!app.world().resource::<Assets<GizmoAsset>>().is_empty());
+
+    app.world_mut().remove_resource::<ControllerDebug>();
+    step(&mut app, 2);
+}
+
+#[test]
+fn try_new_accepts_convex_shapes_and_rejects_the_rest() {
+    let gravity = Vector::NEG_Y * GRAVITY;
+
+    assert!(CharacterControllerBundle::try_new(Collider::capsule(15.0, 30.0), gravity).is_ok());
+    assert_eq!(
+        CharacterControllerBundle::try_new(
+            Collider::segment(Vector::ZERO, Vector::X * 30.0),
+            gravity
+        )
+        .err(),
+        Some(ControllerError::UnsupportedCollider(ShapeType::Segment))
+    );
+}
+
+#[test]
+fn grounded_grace_outlasts_a_missed_ground_cast() {
+    let mut app = test_app();
+    let floor = spawn_floor(&mut app);
+    let plain = spawn_controller(&mut app, controller_bundle(), Vector::Y * STANDING_HEIGHT);
+    let graceful = spawn_controller(
+        &mut app,
+        (controller_bundle(), GroundedGrace(3)),
+        Vector::new(200.0, STANDING_HEIGHT),
+    );
+    step(&mut app, 5);
+    assert!(is_grounded(&app, plain) && is_grounded(&app, graceful));
+
+    app.world_mut().despawn(floor);
+    step(&mut app, 2);
+    assert!(!is_grounded(&app, plain));
+    assert!(is_grounded(&app, graceful));
+
+    step(&mut app, 3);
+    assert!(!is_grounded(&app, graceful));
+}
+
+#[test]
+fn flattest_side_of_a_concave_corner_is_the_ground() {
+    let mut app = test_app();
+    // A V whose sides meet at the origin, a gentle slope on the left and a steeper one on the
+    // right, both shallow enough to stand on
+    spawn_slope(&mut app, -10.0, -1.0);
+    spawn_slope(&mut app, 30.0, 1.0);
+    let controller = spawn_controller(&mut app, controller_bundle(), Vector::Y * 100.0);
+
+    step(&mut app, 120);
+
+    assert!(is_grounded(&app, controller));
+    let normal = component::<GroundNormal>(&app, controller).0.unwrap();
+    assert!((normal.angle_to(Vector::Y).abs().to_degrees() - 10.0).abs() < 1.0);
+}
+
+#[test]
+fn impulses_add_to_the_controller_velocity() {
+    let mut app = test_app();
+    let mut bundle = controller_bundle();
+    bundle.gravity = Gravity(Vector::ZERO);
+    let controller = spawn_controller(&mut app, bundle, Vector::ZERO);
+    insert(&mut app, controller, LinearVelocity(Vector::Y * 50.0));
+
+    app.world_mut().send_event(ApplyImpulse {
+        entity: controller,
+        impulse: Vector::Y * 200.0,
+    });
+    app.update();
+
+    assert!((velocity(&app, controller).y - 250.0).abs() < 1e-3);
+}
+
+#[test]
+fn bounce_pad_launches_a_landing_controller_at_its_impulse() {
+    let mut app = test_app();
+    let pad = spawn_floor(&mut app);
+    insert(&mut app, pad, BouncePad { impulse: 500.0 });
+    let controller = spawn_controller(&mut app, controller_bundle(), Vector::Y * 100.0);
+
+    let mut fastest_rise: Scalar = 0.0;
+    for _ in 0..60 {
+        app.update();
+        fastest_rise = fastest_rise.max(velocity(&app, controller).y);
+    }
+
+    // Gravity takes up to a step's worth off before the velocity is seen
+    assert!(fastest_rise <= 500.0 + 1e-3);
+    assert!(fastest_rise > 500.0 - GRAVITY / 30.0);
+}
+
+#[test]
+fn disabled_gravity_leaves_vertical_velocity_alone() {
+    let mut app = test_app();
+    let controller = spawn_controller(&mut app, controller_bundle(), Vector::ZERO);
+    insert(
+        &mut app,
+        controller,
+        (GravityEnabled(false), LinearVelocity(Vector::Y * 80.0)),
+    );
+
+    for _ in 0..10 {
+        app.update();
+        assert!((velocity(&app, controller).y - 80.0).abs() < 1e-3);
+    }
+}
+
+#[test]
+fn frozen_controller_holds_still_and_resumes_when_thawed() {
+    let mut app = test_app();
+    // The trigger is on a layer the controller doesn't collide with, so it never blocks it
+    let controller = spawn_controller(
+        &mut app,
+        controller_bundle().with_collision_mask(LayerMask(0b01)),
+        Vector::ZERO,
+    );
+    insert(
+        &mut app,
+        controller,
+        LinearVelocity(Vector::new(100.0, 50.0)),
+    );
+    app.update();
+
+    insert(&mut app, controller, Frozen);
+    app.update();
+    let frozen_at = position(&app, controller);
+    let frozen_speed = *component::<Speed>(&app, controller);
+    // A trigger that appears around the frozen controller isn't entered until it thaws
+    let trigger = spawn_box(&mut app, frozen_at, Vector::splat(400.0));
+    insert(
+        &mut app,
+        trigger,
+        (
+            Sensor,
+            CollisionLayers::new(LayerMask(0b10), LayerMask::ALL),
+        ),
+    );
+
+    for _ in 0..10 {
+        app.update();
+        assert_eq!(position(&app, controller), frozen_at);
+        assert_eq!(velocity(&app, controller), Vector::ZERO);
+        assert_eq!(component::<Speed>(&app, controller), &frozen_speed);
+        assert!(drain_events::<TriggerEnter>(&mut app).is_empty());
+    }
+
+    app.world_mut().entity_mut(controller).remove::<Frozen>();
+    app.update();
+    assert!(velocity(&app, controller).x > 90.0);
+    assert!(position(&app, controller).x > frozen_at.x);
+    assert_eq!(
+        drain_events::<TriggerEnter>(&mut app),
+        [TriggerEnter {
+            controller,
+            trigger
+        }]
+    );
+}
+
+#[test]
+fn fast_controller_stops_at_a_thin_wall() {
+    let mut app = test_app();
+    // The wall's left face is at x = 299
+    spawn_box(&mut app, Vector::X * 300.0, Vector::new(2.0, 200.0));
+    let controller = spawn_controller(&mut app, controller_bundle(), Vector::ZERO);
+    insert(
+        &mut app,
+        controller,
+        (GravityEnabled(false), LinearVelocity(Vector::X * 5000.0)),
+    );
+
+    step(&mut app, 20);
+
+    let right_edge = position(&app, controller).x + CONTROLLER_SIZE.x / 2.0;
+    assert!(right_edge <= 299.0);
+    assert!(right_edge > 299.0 - 1.0);
+}
+
+#[test]
+fn controller_slides_along_the_floor_into_an_inside_corner() {
+    let mut app = test_app();
+    spawn_floor(&mut app);
+    // The wall's left face is at x = 200
+    spawn_box(
+        &mut app,
+        Vector::new(210.0, 100.0),
+        Vector::new(20.0, 200.0),
+    );
+    let controller = spawn_controller(&mut app, controller_bundle(), Vector::Y * 100.0);
+    app.update();
+    // No damping, so only the floor and the wall can stop it
+    insert(
+        &mut app,
+        controller,
+        (
+            GravityEnabled(false),
+            MovementDamping(1.0),
+            AirDamping(1.0),
+            LinearVelocity(Vector::new(200.0, -200.0)),
+        ),
+    );
+
+    step(&mut app, 90);
+
+    let right_edge = position(&app, controller).x + CONTROLLER_SIZE.x / 2.0;
+    let bottom = position(&app, controller).y - CONTROLLER_SIZE.y / 2.0;
+    assert!((199.0..=200.0).contains(&right_edge));
+    assert!((0.0..=1.0).contains(&bottom));
+}
+
+#[test]
+fn sliding_into_a_wedge_stops_once_the_leftover_motion_is_negligible() {
+    let mut app = test_app();
+    // A right-angled V, so sliding down one side straight into the other leaves nothing over
+    spawn_slope(&mut app, -45.0, -1.0);
+    spawn_slope(&mut app, 45.0, 1.0);
+    step(&mut app, 2);
+
+    let config = CollideAndSlideConfig {
+        bounces: 16,
+        min_distance: 0.5,
+        ..default()
+    };
+    let bounces = config.bounces;
+    let result = app
+        .world_mut()
+        .run_system_once(move |spatial_query: Res<SpatialQueryPipeline>| {
+            collide_and_slide(
+                &spatial_query,
+                &Collider::circle(10.0),
+                Vector::Y * 30.0,
+                Vector::NEG_Y * 40.0,
+                &config,
+                &|_, _| false,
+            )
+        })
+        .unwrap();
+
+    assert!(result.hits.len() < bounces);
+    // The circle rests where it touches both sides, 10 * sqrt(2) above the bottom
+    let height = 30.0 + result.displacement.y;
+    assert!((height - 10.0 * std::f32::consts::SQRT_2).abs() < 2.0 * SKIN_WIDTH);
+}
+
+#[test]
+fn min_slide_distance_comes_from_the_settings_or_the_override() {
+    let mut app = test_app();
+    let bundle = || {
+        let mut bundle = controller_bundle();
+        bundle.gravity = Gravity(Vector::ZERO);
+        bundle
+    };
+    let tuned = spawn_controller(&mut app, bundle(), Vector::ZERO);
+    let overridden = spawn_controller(
+        &mut app,
+        (
+            bundle(),
+            CollideAndSlideOverride(CollideAndSlideSettings::default()),
+        ),
+        Vector::new(200.0, 0.0),
+    );
+
+    // Each step moves 100 / 60 units, too little to bother sliding with the raised minimum
+    app.world_mut()
+        .resource_mut::<CollideAndSlideSettings>()
+        .min_slide_distance = 10.0;
+    for _ in 0..5 {
+        for controller in [tuned, overridden] {
+            insert(&mut app, controller, LinearVelocity(Vector::X * 100.0));
+        }
+        app.update();
+    }
+
+    assert_eq!(position(&app, tuned), Vector::ZERO);
+    assert!(position(&app, overridden).x > 205.0);
+}
+#[test]
+fn controller_cannot_walk_up_a_slope_steeper_than_its_max() {
+    let mut app = test_app();
+    spawn_floor(&mut app);
+    spawn_slope(&mut app, 70.0, 1.0);
+    let controller = spawn_controller(
+        &mut app,
+        controller_bundle(),
+        Vector::new(-100.0, STANDING_HEIGHT),
+    );
+    app.update();
+    insert(&mut app, controller, MaxSlopeAngle(60.0_f32.to_radians()));
+
+    let mut highest = position(&app, controller).y;
+    for _ in 0..120 {
+        send(&mut app, controller, MovementAction::Walk(1.0));
+        app.update();
+        highest = highest.max(position(&app, controller).y);
+    }
+
+    assert!(position(&app, controller).x > -50.0);
+    assert!(highest < STANDING_HEIGHT + 5.0);
+}
+
+#[test]
+fn contacts_are_classified_relative_to_up() {
+    let max_slope_angle = 45.0_f32.to_radians();
+    let classify = |normal: Vector, up: Dir2| ContactKind::classify(normal, up, max_slope_angle);
+
+    assert_eq!(classify(Vector::Y, Dir2::Y), ContactKind::Floor);
+    assert_eq!(classify(Vector::NEG_X, Dir2::Y), ContactKind::Wall);
+    assert_eq!(classify(Vector::NEG_Y, Dir2::Y), ContactKind::Ceiling);
+    assert_eq!(
+        classify(Vector::new(1.0, 2.0).normalize(), Dir2::Y),
+        ContactKind::Floor
+    );
+    // Sideways gravity turns the wall into the floor
+    assert_eq!(classify(Vector::NEG_X, Dir2::NEG_X), ContactKind::Floor);
+}
+
+#[test]
+fn contact_state_records_floors_walls_and_ceilings() {
+    let mut app = test_app();
+    // A floor, a wall and a ceiling, each in its own lane
+    spawn_box(&mut app, Vector::new(0.0, -50.0), Vector::new(100.0, 20.0));
+    spawn_box(&mut app, Vector::new(500.0, 0.0), Vector::new(20.0, 100.0));
+    spawn_box(
+        &mut app,
+        Vector::new(1000.0, 100.0),
+        Vector::new(100.0, 20.0),
+    );
+    let movers = [
+        (Vector::ZERO, Vector::NEG_Y, ContactKind::Floor),
+        (Vector::X * 450.0, Vector::X, ContactKind::Wall),
+        (Vector::X * 1000.0, Vector::Y, ContactKind::Ceiling),
+    ]
+    .map(|(position, direction, kind)| {
+        let controller = spawn_controller(&mut app, controller_bundle(), position);
+        insert(&mut app, controller, GravityEnabled(false));
+        (controller, direction, kind)
+    });
+
+    for _ in 0..30 {
+        for &(controller, direction, _) in &movers {
+            insert(&mut app, controller, LinearVelocity(direction * 200.0));
+        }
+        app.update();
+    }
+
+    for (controller, _, kind) in movers {
+        let contacts = component::<ContactState>(&app, controller);
+        assert!(contacts.iter().all(|contact| contact.kind == kind));
+        assert!(contacts.touching(kind), "{kind:?} wasn't recorded");
+    }
+}
+
+#[test]
+fn fast_fall_pulls_an_airborne_controller_down_harder() {
+    fn fall_after_three_steps(fast_fall: bool) -> Scalar {
+        let mut app = test_app();
+        let controller = spawn_controller(&mut app, controller_bundle(), Vector::ZERO);
+        for _ in 0..3 {
+            if fast_fall {
+                send(&mut app, controller, MovementAction::FastFall);
+            }
+            app.update();
+        }
+        velocity(&app, controller).y
+    }
+
+    let normal_fall = fall_after_three_steps(false);
+    let fast_fall = fall_after_three_steps(true);
+    assert!(normal_fall < 0.0);
+    assert!(fast_fall < normal_fall);
+}
+
+#[test]
+fn facing_follows_walking_and_holds_when_stopped() {
+    let mut app = test_app();
+    let controller = spawn_controller(&mut app, controller_bundle(), Vector::ZERO);
+    insert(&mut app, controller, GravityEnabled(false));
+    let walk = |app: &mut App, direction: Scalar| {
+        for _ in 0..10 {
+            send(app, controller, MovementAction::Walk(direction));
+            app.update();
+        }
+    };
+
+    walk(&mut app, -1.0);
+    assert_eq!(*component::<Facing>(&app, controller), Facing::Left);
+
+    step(&mut app, 60);
+    assert_eq!(*component::<Facing>(&app, controller), Facing::Left);
+
+    walk(&mut app, 1.0);
+    assert_eq!(*component::<Facing>(&app, controller), Facing::Right);
+}
+
+#[test]
+fn plugin_sets_the_fixed_timestep_rate() {
+    let mut app = App::new();
+    app.add_plugins((MinimalPlugins, CharacterControllerPlugin::new(120.0)));
+
+    assert_eq!(
+        app.world().resource::<Time<Fixed>>().timestep(),
+        Duration::from_secs_f64(1.0 / 120.0)
+    );
+}
+
+#[test]
+fn running_jumps_go_higher_with_a_speed_bonus() {
+    let mut app = test_app();
+    spawn_floor(&mut app);
+    let [standing, running] = [0.0, 200.0].map(|x| {
+        spawn_controller(
+            &mut app,
+            (controller_bundle(), JumpSpeedBonus(0.5)),
+            Vector::new(x, STANDING_HEIGHT),
+        )
+    });
+    step(&mut app, 5);
+
+    insert(&mut app, running, LinearVelocity(Vector::X * 300.0));
+    for controller in [standing, running] {
+        send(&mut app, controller, MovementAction::Jump);
+    }
+    app.update();
+
+    assert!(velocity(&app, standing).y > 0.0);
+    assert!(velocity(&app, running).y > velocity(&app, standing).y);
+}
+
+#[test]
+fn moving_one_way_platform_carries_riders_and_lets_jumps_through() {
+    /// Spawns a one-way platform at `position` rising at 30 units per second.
+    fn spawn_rising_platform(app: &mut App, position: Vector) -> Entity {
+        app.world_mut()
+            .spawn((
+                RigidBody::Kinematic,
+                Collider::rectangle(200.0, 20.0),
+                LinearVelocity(Vector::Y * 30.0),
+                OneWayPlatform,
+                Transform::from_translation(position.extend(0.0)),
+            ))
+            .id()
+    }
+
+    let mut app = test_app();
+    spawn_floor(&mut app);
+    // The rider starts on its platform, the jumper on the floor under its own
+    let rider_platform = spawn_rising_platform(&mut app, Vector::Y * 60.0);
+    let rider = spawn_controller(
+        &mut app,
+        controller_bundle(),
+        Vector::Y * (70.0 + STANDING_HEIGHT),
+    );
+    let jumper_platform = spawn_rising_platform(&mut app, Vector::new(1000.0, 130.0));
+    let jumper = spawn_controller(
+        &mut app,
+        controller_bundle(),
+        Vector::new(1000.0, STANDING_HEIGHT),
+    );
+    step(&mut app, 5);
+    insert(&mut app, jumper, JumpImpulse(600.0));
+    send(&mut app, jumper, MovementAction::Jump);
+
+    step(&mut app, 120);
+
+    for (controller, platform) in [(rider, rider_platform), (jumper, jumper_platform)] {
+        assert!(is_grounded(&app, controller));
+        assert_eq!(
+            component::<RidingPlatform>(&app, controller).0,
+            Some(platform)
+        );
+        let height_above = position(&app, controller).y - position(&app, platform).y;
+        assert!((height_above - (10.0 + STANDING_HEIGHT)).abs() < 1.0);
+    }
+}
+
+#[test]
+fn riding_platform_is_the_collider_stood_on() {
+    let mut app = test_app();
+    let floor = spawn_floor(&mut app);
+    let platform = spawn_box(&mut app, Vector::new(500.0, 50.0), Vector::new(100.0, 20.0));
+    let on_floor = spawn_controller(&mut app, controller_bundle(), Vector::Y * 100.0);
+    let on_platform = spawn_controller(&mut app, controller_bundle(), Vector::new(500.0, 150.0));
+    assert_eq!(component::<RidingPlatform>(&app, on_floor).0, None);
+
+    step(&mut app, 60);
+
+    assert_eq!(component::<RidingPlatform>(&app, on_floor).0, Some(floor));
+    assert_eq!(
+        component::<RidingPlatform>(&app, on_platform).0,
+        Some(platform)
+    );
+}
+
+#[test]
+fn overlapping_controllers_push_each_other_apart() {
+    let mut app = test_app();
+    app.init_resource::<ControllerPushConfig>();
+    let [left, right] = [0.0, 10.0].map(|x| {
+        let controller = spawn_controller(&mut app, controller_bundle(), Vector::X * x);
+        insert(&mut app, controller, GravityEnabled(false));
+        controller
+    });
+
+    step(&mut app, 5);
+
+    let gap = position(&app, right).x - position(&app, left).x;
+    assert!(gap >= CONTROLLER_SIZE.x - 0.1);
+    // Each controller moves half of the way
+    assert!((position(&app, left).x + position(&app, right).x - 10.0).abs() < 0.1);
+}
+
+#[test]
+fn up_direction_makes_a_wall_the_floor() {
+    let mut app = test_app();
+    // Two walls whose right faces are at x = -90 and x = 410
+    spawn_box(&mut app, Vector::new(-100.0, 0.0), Vector::new(20.0, 400.0));
+    spawn_box(&mut app, Vector::new(400.0, 0.0), Vector::new(20.0, 400.0));
+    let [upright, sideways] = [-74.5, 425.5].map(|x| {
+        let controller = spawn_controller(&mut app, controller_bundle(), Vector::X * x);
+        insert(&mut app, controller, GravityEnabled(false));
+        controller
+    });
+    insert(&mut app, sideways, UpDirection(Dir2::X));
+
+    step(&mut app, 5);
+
+    assert!(!is_grounded(&app, upright));
+    assert!(is_grounded(&app, sideways));
+    let normal = component::<GroundNormal>(&app, sideways).0.unwrap();
+    assert!(normal.dot(Vector::X) > 0.999);
+}
+
+#[test]
+fn terminal_velocity_follows_the_up_direction() {
+    let mut velocity = Vector::X * 50.0;
+    for _ in 0..600 {
+        velocity = gravity_step(
+            velocity,
+            Vector::Y * GRAVITY,
+            Dir2::NEG_Y,
+            300.0,
+            1.0 / 60.0,
+        );
+    }
+
+    // Flipped gravity saturates upwards, and sideways motion isn't clamped
+    assert_eq!(velocity, Vector::new(50.0, 300.0));
+}
+
+#[test]
+fn flipped_gravity_falls_up_onto_the_ceiling() {
+    let mut app = test_app();
+    spawn_floor(&mut app);
+    // The ceiling's bottom is at y = 200
+    spawn_box(&mut app, Vector::Y * 210.0, Vector::new(4000.0, 20.0));
+    let controller = spawn_controller(&mut app, controller_bundle(), Vector::Y * STANDING_HEIGHT);
+    step(&mut app, 5);
+    assert!(is_grounded(&app, controller));
+
+    send(&mut app, controller, MovementAction::FlipGravity);
+    step(&mut app, 3);
+    assert!(velocity(&app, controller).y > 0.0);
+
+    step(&mut app, 60);
+    assert!(is_grounded(&app, controller));
+    assert!((position(&app, controller).y - (200.0 - STANDING_HEIGHT)).abs() < 1.0);
+    let normal = component::<GroundNormal>(&app, controller).0.unwrap();
+    assert!(normal.dot(Vector::NEG_Y) > 0.999);
+}
+
+#[test]
+fn walking_into_a_jump_sends_one_state_change() {
+    use CharacterControllerState as State;
+
+    let mut app = test_app();
+    spawn_floor(&mut app);
+    let controller = spawn_controller(&mut app, controller_bundle(), Vector::Y * STANDING_HEIGHT);
+    step(&mut app, 5);
+    drain_events::<StateChanged>(&mut app);
+
+    let mut changes = Vec::new();
+    for frame in 0..20 {
+        send(&mut app, controller, MovementAction::Walk(1.0));
+        if frame == 10 {
+            send(&mut app, controller, MovementAction::Jump);
+        }
+        app.update();
+        changes.extend(drain_events::<StateChanged>(&mut app));
+    }
+
+    let walk_to_jump = StateChanged {
+        entity: controller,
+        from: State::Walking,
+        to: State::Jumping,
+    };
+    assert_eq!(
+        changes
+            .iter()
+            .filter(|&&change| change == walk_to_jump)
+            .count(),
+        1
+    );
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn controller_config_round_trips_through_ron() {
+    let config = ControllerConfig {
+        gravity: 1500.0,
+        acceleration: 2000.0,
+        jump_impulse: 500.0,
+        air_damping: 0.95,
+        ..default()
+    };
+
+    let ron = ron::to_string(&config).unwrap();
+    let loaded: ControllerConfig = ron::from_str(&ron).unwrap();
+    assert_eq!(loaded, config);
+
+    let bundle = CharacterControllerBundle::from_config(loaded);
+    assert_eq!(bundle.gravity.0, Vector::NEG_Y * 1500.0);
+    assert_eq!(bundle.movement.acceleration.0, 2000.0);
+    assert_eq!(bundle.movement.jump_impulse.0, 500.0);
+    assert_eq!(bundle.air_damping.0, 0.95);
+}
+
+#[test]
+fn presets_differ_in_feel() {
+    let precise = MovementBundle::precise();
+    let floaty = MovementBundle::floaty();
+    let momentum = MovementBundle::momentum();
+
+    // Damping is the fraction of speed kept, so floatier presets keep more of it
+    assert!(floaty.damping.0 > precise.damping.0);
+    assert!(floaty.jump_impulse.0 < precise.jump_impulse.0);
+    assert!(momentum.acceleration.0 < precise.acceleration.0);
+    assert!(momentum.damping.0 > floaty.damping.0);
+}
+
+#[test]
+fn air_time_counts_up_in_the_air_and_resets_on_landing() {
+    let mut app = test_app();
+    spawn_floor(&mut app);
+    let controller = spawn_controller(&mut app, controller_bundle(), Vector::Y * 200.0);
+
+    step(&mut app, 10);
+    let air_time = component::<AirTime>(&app, controller).0;
+    assert!(air_time > 0.1);
+
+    step(&mut app, 10);
+    assert!(component::<AirTime>(&app, controller).0 > air_time);
+
+    step(&mut app, 60);
+    assert!(is_grounded(&app, controller));
+    assert_eq!(component::<AirTime>(&app, controller).0, 0.0);
+}
+
+#[test]
+fn coyote_time_and_jump_buffer_durations_come_from_the_builder() {
+    let mut app = test_app();
+    let controller = spawn_controller(
+        &mut app,
+        controller_bundle()
+            .with_coyote_time(0.25)
+            .with_jump_buffer(0.15),
+        Vector::ZERO,
+    );
+
+    assert_eq!(component::<CoyoteTime>(&app, controller).duration, 0.25);
+    assert_eq!(component::<JumpBuffer>(&app, controller).duration, 0.15);
+    assert_eq!(component::<CoyoteTime>(&app, controller).remaining(), 0.0);
+    assert_eq!(component::<JumpBuffer>(&app, controller).remaining(), 0.0);
+}
+
+#[test]
+fn dropping_through_a_one_way_platform_lands_below_it() {
+    let mut app = test_app();
+    spawn_floor(&mut app);
+    // The platform's top is at y = 110
+    let platform = spawn_box(&mut app, Vector::Y * 100.0, Vector::new(200.0, 20.0));
+    insert(&mut app, platform, OneWayPlatform);
+    let controller = spawn_controller(
+        &mut app,
+        controller_bundle(),
+        Vector::Y * (110.0 + STANDING_HEIGHT),
+    );
+    step(&mut app, 5);
+    assert_eq!(
+        component::<RidingPlatform>(&app, controller).0,
+        Some(platform)
+    );
+
+    send(&mut app, controller, MovementAction::DropThrough);
+    step(&mut app, 60);
+
+    assert!(is_grounded(&app, controller));
+    assert!((position(&app, controller).y - STANDING_HEIGHT).abs() < 1.0);
+}
+
+#[test]
+fn jump_height_does_not_depend_on_the_physics_rate() {
+    let [slow, fast] = [30.0, 120.0].map(|hz| {
+        let mut app = test_app_with(CharacterControllerPlugin::new(hz));
+        spawn_floor(&mut app);
+        let controller = spawn_controller(
+            &mut app,
+            controller_bundle(),
+            Vector::new(0.0, STANDING_HEIGHT),
+        );
+        step(&mut app, hz as usize / 2);
+        let start = position(&app, controller).y;
+
+        send(&mut app, controller, MovementAction::Jump);
+        let mut peak = start;
+        for _ in 0..2 * hz as usize {
+            app.update();
+            peak = peak.max(position(&app, controller).y);
+        }
+        peak - start
+    });
+
+    assert!(slow > 50.0);
+    assert!((slow - fast).abs() < 1.0, "{slow} at 30hz, {fast} at 120hz");
+}
+
+/// Drops a controller with a [`HardLandingThreshold`] of 200 from `height` above the floor, and
+/// returns whether it ever had a [`HardLanding`].
+fn lands_hard_from(height: Scalar) -> bool {
+    let mut app = test_app();
+    spawn_floor(&mut app);
+    let controller = spawn_controller(
+        &mut app,
+        (controller_bundle(), HardLandingThreshold(200.0)),
+        Vector::new(0.0, STANDING_HEIGHT + height),
+    );
+
+    (0..180).any(|_| {
+        app.update();
+        app.world().entity(controller).contains::<HardLanding>()
+    })
+}
+
+#[test]
+fn long_falls_land_hard_and_short_ones_dont() {
+    assert!(lands_hard_from(600.0));
+    assert!(!lands_hard_from(30.0));
+}
+
+#[test]
+fn respawning_mid_fall_measures_the_fall_from_the_respawn_point() {
+    let mut app = test_app();
+    spawn_floor(&mut app);
+    let controller = spawn_controller(
+        &mut app,
+        (controller_bundle(), HardLandingThreshold(200.0)),
+        Vector::new(0.0, STANDING_HEIGHT + 600.0),
+    );
+    step(&mut app, 20);
+
+    app.world_mut().send_event(RespawnEvent {
+        entity: controller,
+        position: Vector::new(0.0, STANDING_HEIGHT + 30.0),
+    });
+    let mut landings = Vec::new();
+    let mut landed_hard = false;
+    for _ in 0..60 {
+        app.update();
+        landed_hard |= app.world().entity(controller).contains::<HardLanding>();
+        landings.extend(drain_events::<LandEvent>(&mut app));
+    }
+
+    assert!(!landed_hard);
+    assert!(!landings.is_empty());
+    assert!(landings.iter().all(|landing| landing.fall_distance < 40.0));
+}
+
+#[test]
+fn each_player_moves_only_with_their_own_keys() {
+    let mut app = test_app();
+    app.init_resource::<ButtonInput<KeyCode>>();
+    spawn_floor(&mut app);
+    let first = spawn_controller(&mut app, controller_bundle(), Vector::Y * STANDING_HEIGHT);
+    let second = spawn_controller(
+        &mut app,
+        (
+            controller_bundle().with_player(1),
+            InputBindings {
+                left: vec![KeyCode::KeyJ],
+                right: vec![KeyCode::KeyL],
+                ..default()
+            },
+        ),
+        Vector::new(200.0, STANDING_HEIGHT),
+    );
+    step(&mut app, 5);
+
+    /// Holds `key` for half a second, lets go for as long, and returns how far each controller
+    /// moved along x.
+    fn hold(app: &mut App, key: KeyCode, controllers: [Entity; 2]) -> [Scalar; 2] {
+        let start = controllers.map(|controller| position(app, controller).x);
+        app.world_mut()
+            .resource_mut::<ButtonInput<KeyCode>>()
+            .press(key);
+        step(app, 30);
+        app.world_mut()
+            .resource_mut::<ButtonInput<KeyCode>>()
+            .reset_all();
+        step(app, 30);
+
+        [0, 1].map(|i| position(app, controllers[i]).x - start[i])
+    }
+
+    let [first_moved, second_moved] = hold(&mut app, KeyCode::KeyD, [first, second]);
+    assert!(first_moved > 10.0);
+    assert!(second_moved.abs() < 1.0);
+
+    let [first_moved, second_moved] = hold(&mut app, KeyCode::KeyL, [first, second]);
+    assert!(first_moved.abs() < 1.0);
+    assert!(second_moved > 10.0);
+}
+
+#[test]
+fn jumping_into_an_overhang_slides_along_it_without_jitter() {
+    let mut app = test_app();
+    spawn_floor(&mut app);
+    // A 30 degree overhang whose underside rises to the right, 110 units up above the origin
+    let rotation = Rot2::degrees(30.0);
+    app.world_mut().spawn((
+        RigidBody::Static,
+        Collider::rectangle(400.0, 20.0),
+        Transform::from_translation((Vector::Y * 110.0 + rotation * Vector::Y * 10.0).extend(0.0))
+            .with_rotation(Quat::from_rotation_z(rotation.as_radians())),
+    ));
+    let controller = spawn_controller(&mut app, controller_bundle(), Vector::Y * STANDING_HEIGHT);
+    step(&mut app, 5);
+
+    send(&mut app, controller, MovementAction::Jump);
+    let mut xs = Vec::new();
+    for _ in 0..30 {
+        app.update();
+        xs.push(position(&app, controller).x);
+    }
+
+    assert!(xs.last().unwrap() - xs[0] > 5.0);
+    assert!(
+        xs.windows(2).all(|pair| pair[1] >= pair[0] - 1e-3),
+        "{xs:?}"
+    );
+}
+
+#[test]
+fn teleporting_flush_against_the_floor_doesnt_shove_the_controller() {
+    let mut app = test_app();
+    spawn_floor(&mut app);
+    let controller = spawn_controller(&mut app, controller_bundle(), Vector::new(0.0, 300.0));
+    step(&mut app, 10);
+    assert!(velocity(&app, controller).y < 0.0);
+
+    let target = Vector::new(100.0, CONTROLLER_SIZE.y / 2.0);
+    app.world_mut()
+        .run_system_once(move |mut commands: Commands| teleport(&mut commands, controller, target))
+        .unwrap();
+    app.update();
+
+    assert!(position(&app, controller).distance(target) < 1e-3);
+    assert_eq!(velocity(&app, controller), Vector::ZERO);
+    assert!(!app.world().entity(controller).contains::<SkipCollision>());
+
+    step(&mut app, 30);
+    assert!(position(&app, controller).y > target.y - 1.0);
+}
+
+#[test]
+fn ground_normal_count_sets_the_casters_max_hits() {
+    let mut app = test_app();
+    spawn_floor(&mut app);
+    let [few, none] = [(2, -100.0), (0, 100.0)].map(|(count, x)| {
+        spawn_controller(
+            &mut app,
+            controller_bundle().with_ground_normal_count(count),
+            Vector::new(x, STANDING_HEIGHT),
+        )
+    });
+    step(&mut app, 5);
+
+    assert_eq!(component::<ShapeCaster>(&app, few).max_hits, 2);
+    // At least one hit is always collected, or the controller could never be grounded
+    assert_eq!(component::<ShapeCaster>(&app, none).max_hits, 1);
+    assert!(component::<ShapeHits>(&app, few).len() <= 2);
+    assert!(is_grounded(&app, few) && is_grounded(&app, none));
+}
+
+#[test]
+fn squash_stretch_stretches_while_rising_and_squashes_on_landing() {
+    let mut app = test_app();
+    spawn_floor(&mut app);
+    let jumper = spawn_controller(
+        &mut app,
+        (controller_bundle(), SquashStretch::default()),
+        Vector::new(-100.0, STANDING_HEIGHT),
+    );
+    let faller = spawn_controller(
+        &mut app,
+        (controller_bundle(), SquashStretch::default()),
+        Vector::new(100.0, STANDING_HEIGHT + 200.0),
+    );
+    step(&mut app, 5);
+    assert_eq!(component::<SquashStretch>(&app, jumper).scale, Vec2::ONE);
+
+    send(&mut app, jumper, MovementAction::Jump);
+    app.update();
+    let rising = component::<SquashStretch>(&app, jumper).scale;
+    assert!(rising.y > 1.0 && rising.x < 1.0);
+
+    let squashed = (0..60).any(|_| {
+        app.update();
+        let scale = component::<SquashStretch>(&app, faller).scale;
+        scale.y < 1.0 && scale.x > 1.0
+    });
+    assert!(squashed);
+}
+
+#[test]
+fn taps_from_every_frame_between_fixed_steps_are_sent() {
+    let mut app = test_app();
+    app.init_resource::<ButtonInput<KeyCode>>();
+    record_actions(&mut app);
+    // Two frames per fixed step
+    app.insert_resource(TimeUpdateStrategy::ManualDuration(Duration::from_secs_f64(
+        1.0 / 120.0,
+    )));
+
+    for _ in 0..2 {
+        let mut input = app.world_mut().resource_mut::<ButtonInput<KeyCode>>();
+        input.clear();
+        input.release(KeyCode::Space);
+        input.press(KeyCode::Space);
+        app.update();
+    }
+    // A frame without a tap, in case rounding left the second one just short of the step
+    app.world_mut()
+        .resource_mut::<ButtonInput<KeyCode>>()
+        .clear();
+    app.update();
+
+    let jumps = app
+        .world()
+        .resource::<SentActions>()
+        .0
+        .iter()
+        .filter(|&&action| action == MovementAction::Jump)
+        .count();
+    assert_eq!(jumps, 2);
+}
+
+#[test]
+fn tiny_residual_velocity_doesnt_move_the_controller() {
+    let mut app = test_app();
+    let [resting, moving] = [-100.0, 100.0].map(|x| {
+        spawn_controller(
+            &mut app,
+            (controller_bundle(), GravityEnabled(false)),
+            Vector::new(x, 100.0),
+        )
+    });
+    step(&mut app, 2);
+    let start = [resting, moving].map(|controller| *component::<Transform>(&app, controller));
+
+    // Well under `MIN_MOVEMENT_DISTANCE` per step, and well over it
+    insert(&mut app, resting, LinearVelocity(Vector::X * 0.1));
+    insert(&mut app, moving, LinearVelocity(Vector::X * 60.0));
+    app.update();
+
+    assert_eq!(*component::<Transform>(&app, resting), start[0]);
+    assert_eq!(velocity(&app, resting), Vector::ZERO);
+    assert!(component::<Transform>(&app, moving).translation.x > start[1].translation.x);
+}
+
+#[test]
+fn speed_reports_the_velocity_after_collisions() {
+    let mut app = test_app();
+    spawn_floor(&mut app);
+    let controller = spawn_controller(&mut app, controller_bundle(), Vector::Y * STANDING_HEIGHT);
+    step(&mut app, 5);
+    assert_eq!(component::<Speed>(&app, controller).direction, None);
+
+    for _ in 0..20 {
+        send(&mut app, controller, MovementAction::Walk(1.0));
+        app.update();
+    }
+    send(&mut app, controller, MovementAction::Jump);
+    app.update();
+
+    let speed = *component::<Speed>(&app, controller);
+    let velocity = velocity(&app, controller);
+    assert!((speed.total - velocity.length()).abs() < 1e-3);
+    assert!((speed.horizontal - velocity.x.abs()).abs() < 1e-3);
+    assert!(speed.horizontal > 0.0 && speed.total > speed.horizontal);
+    assert_eq!(speed.direction, Dir2::new(velocity).ok());
+}
+
+#[test]
+fn falling_past_a_ledge_grabs_it_and_climbing_stands_on_top() {
+    let mut app = test_app();
+    // A wall whose face is at x = 50 and whose top is at y = 200
+    spawn_box(
+        &mut app,
+        Vector::new(150.0, 100.0),
+        Vector::new(200.0, 200.0),
+    );
+    // Against the wall, with its top just above the ledge
+    let controller = spawn_controller(
+        &mut app,
+        (controller_bundle(), LedgeGrab),
+        Vector::new(34.0, 200.0 + 4.0 - CONTROLLER_SIZE.y / 2.0),
+    );
+
+    let grabbed = (0..5).any(|_| {
+        send(&mut app, controller, MovementAction::Walk(1.0));
+        app.update();
+        state(&app, controller) == CharacterControllerState::LedgeHang
+    });
+    assert!(grabbed);
+    let hanging_at = position(&app, controller);
+    step(&mut app, 10);
+    assert!(position(&app, controller).distance(hanging_at) < 1e-3);
+
+    send(&mut app, controller, MovementAction::Climb);
+    step(&mut app, 10);
+
+    assert!(position(&app, controller).x > 50.0);
+    assert!(position(&app, controller).y > 200.0);
+    assert!(is_grounded(&app, controller));
+    assert!(!app.world().entity(controller).contains::<Hanging>());
+}
+
+#[test]
+fn jump_state_tracks_whether_a_jump_is_possible() {
+    let mut app = test_app();
+    spawn_floor(&mut app);
+    let controller = spawn_controller(
+        &mut app,
+        (controller_bundle(), AirJumps(1)),
+        Vector::Y * STANDING_HEIGHT,
+    );
+    step(&mut app, 5);
+
+    let grounded = *component::<JumpState>(&app, controller);
+    assert!(grounded.can_jump);
+    assert_eq!(grounded.max_air_jumps, 1);
+
+    send(&mut app, controller, MovementAction::Jump);
+    step(&mut app, 10);
+    assert!(!is_grounded(&app, controller));
+    assert!(component::<JumpState>(&app, controller).can_jump);
+
+    send(&mut app, controller, MovementAction::Jump);
+    app.update();
+    let exhausted = *component::<JumpState>(&app, controller);
+    assert_eq!(exhausted.air_jumps_used, 1);
+    assert!(!exhausted.can_jump);
+}
+
+#[test]
+fn controllers_without_a_keyboard_move_on_programmatic_actions() {
+    /// Marks a controller driven by [`walk_left`] instead of a player.
+    #[derive(Component)]
+    struct Brain;
+
+    fn walk_left(
+        brains: Query<Entity, With<Brain>>,
+        mut action_events: EventWriter<ControllerAction>,
+    ) {
+        for entity in &brains {
+            action_events.write(ControllerAction::controller(
+                entity,
+                MovementAction::Walk(-1.0),
+            ));
+        }
+    }
+
+    let mut app = test_app();
+    app.init_resource::<ButtonInput<KeyCode>>()
+        .add_systems(FixedUpdate, walk_left.before(ControllerSet::Movement));
+    spawn_floor(&mut app);
+    // Player 1 has no keyboard bindings, so only the brain drives it
+    let controller = spawn_controller(
+        &mut app,
+        (controller_bundle().with_player(1), Brain),
+        Vector::Y * STANDING_HEIGHT,
+    );
+    step(&mut app, 5);
+    let start = position(&app, controller);
+
+    // Player 0's keys don't reach it
+    app.world_mut()
+        .resource_mut::<ButtonInput<KeyCode>>()
+        .press(KeyCode::KeyD);
+    step(&mut app, 30);
+
+    assert!(position(&app, controller).x < start.x - 10.0);
+    assert!(is_grounded(&app, controller));
+}
+
+#[test]
+fn low_friction_surfaces_keep_the_controller_sliding_longer() {
+    let mut app = test_app();
+    let ice = spawn_box(
+        &mut app,
+        Vector::new(-1000.0, -10.0),
+        Vector::new(1000.0, 20.0),
+    );
+    insert(&mut app, ice, SurfaceFriction(0.1));
+    spawn_box(
+        &mut app,
+        Vector::new(1000.0, -10.0),
+        Vector::new(1000.0, 20.0),
+    );
+    let [on_ice, on_ground] = [-1000.0, 1000.0].map(|x| {
+        spawn_controller(
+            &mut app,
+            controller_bundle(),
+            Vector::new(x, STANDING_HEIGHT),
+        )
+    });
+    step(&mut app, 5);
+
+    for controller in [on_ice, on_ground] {
+        insert(&mut app, controller, LinearVelocity(Vector::X * 200.0));
+    }
+    step(&mut app, 20);
+
+    assert!(velocity(&app, on_ice).x > velocity(&app, on_ground).x + 10.0);
+    assert!(position(&app, on_ice).x + 1000.0 > position(&app, on_ground).x - 1000.0);
+}
+
+#[test]
+fn pushing_controllers_shove_dynamic_bodies() {
+    let mut app = test_app();
+    spawn_floor(&mut app);
+    let [pusher, blocked] = [(-1000.0, true), (1000.0, false)].map(|(x, push)| {
+        spawn_controller(
+            &mut app,
+            (controller_bundle(), PushDynamicBodies(push)),
+            Vector::new(x, STANDING_HEIGHT),
+        )
+    });
+    let [pushed, unpushed] = [-950.0, 1050.0].map(|x| {
+        app.world_mut()
+            .spawn((
+                RigidBody::Dynamic,
+                Collider::rectangle(20.0, 20.0),
+                Transform::from_xyz(x, 10.0, 0.0),
+            ))
+            .id()
+    });
+    step(&mut app, 5);
+
+    let mut box_speed = [0.0; 2];
+    for _ in 0..60 {
+        for controller in [pusher, blocked] {
+            send(&mut app, controller, MovementAction::Walk(1.0));
+        }
+        app.update();
+        for (speed, body) in box_speed.iter_mut().zip([pushed, unpushed]) {
+            *speed = Scalar::max(*speed, velocity(&app, body).x);
+        }
+    }
+
+    assert!(box_speed[0] > 10.0);
+    assert!(position(&app, pushed).x > -940.0);
+    assert!(box_speed[1] < 1.0);
+}
+
+#[test]
+fn rising_gravity_accounts_for_the_slower_apex() {
+    let gravity = Gravity(Vector::NEG_Y * 1000.0);
+    let fast_fall = FastFallMultiplier(2.0);
+    let plain = RisingGravity::new(&gravity, (None, None, &fast_fall), false);
+    assert_eq!(
+        plain.jump_speed_for_height(100.0),
+        jump_speed_for_height(100.0, 1000.0)
+    );
+
+    // Half gravity for the first 1.6 units below the apex, then full gravity for 100 more
+    let apex = (ApexGravityMultiplier(0.5), ApexThreshold(40.0));
+    let hanging = RisingGravity::new(
+        &gravity,
+        (None, Some((&apex.0, &apex.1)), &fast_fall),
+        false,
+    );
+    let expected = Scalar::sqrt(40.0 * 40.0 + 2.0 * 1000.0 * 100.0);
+    assert!((hanging.jump_speed_for_height(101.6) - expected).abs() < 1e-2);
+
+    let fast_falling = RisingGravity::new(&gravity, (None, None, &fast_fall), true);
+    assert_eq!(
+        fast_falling.jump_speed_for_height(100.0),
+        jump_speed_for_height(100.0, 2000.0)
+    );
+}
+
+/// Jumps a controller with snappy, hanging jumps between `MinJumpHeight(40.0)` and
+/// `MaxJumpHeight(120.0)`, holding jump for the whole rise or not at all, and returns how high
+/// it got.
+fn jump_height(hold: bool) -> Scalar {
+    let mut app = test_app();
+    spawn_floor(&mut app);
+    let controller = spawn_controller(
+        &mut app,
+        (
+            controller_bundle(),
+            JumpSnappiness(1.0),
+            ApexGravityMultiplier::default(),
+            ApexThreshold::default(),
+            MinJumpHeight(40.0),
+            MaxJumpHeight(120.0),
+        ),
+        Vector::Y * STANDING_HEIGHT,
+    );
+    step(&mut app, 5);
+    let start = position(&app, controller).y;
+
+    send(&mut app, controller, MovementAction::Jump);
+    let mut peak = start;
+    for _ in 0..90 {
+        if hold {
+            send(&mut app, controller, MovementAction::JumpHeld);
+        }
+        app.update();
+        peak = peak.max(position(&app, controller).y);
+    }
+    peak - start
+}
+
+#[test]
+fn tapped_jumps_reach_the_minimum_height_and_held_ones_the_maximum() {
+    // The cut leaves the minimum height to go from wherever the controller is by then
+    let tapped = jump_height(false);
+    assert!((39.0..50.0).contains(&tapped), "{tapped}");
+    let held = jump_height(true);
+    assert!((held - 120.0).abs() < 3.0, "{held}");
+}
+
+#[test]
+fn debug_text_follows_the_controller_state() {
+    let mut app = test_app_with(CharacterControllerPlugin {
+        debug: true,
+        ..CharacterControllerPlugin::new(60.0)
+    });
+    spawn_floor(&mut app);
+    let controller = spawn_controller(
+        &mut app,
+        (controller_bundle(), ControllerDebugText),
+        Vector::Y * STANDING_HEIGHT,
+    );
+    step(&mut app, 5);
+
+    let label = component::<DebugTextLabel>(&app, controller).0;
+    assert_eq!(component::<ChildOf>(&app, label).parent(), controller);
+    let text = |app: &App| component::<Text2d>(app, label).0.clone();
+    assert!(
+        text(&app).starts_with("Idle\ngrounded: true"),
+        "{}",
+        text(&app)
+    );
+
+    send(&mut app, controller, MovementAction::Jump);
+    app.update();
+    assert!(text(&app).starts_with("Jumping"), "{}", text(&app));
+}
+
+#[test]
+fn capsules_fill_their_box_along_either_axis() {
+    let (vertical, rotation) = capsule_from_size(Vector::new(30.0, 60.0), CapsuleAxis::Vertical);
+    assert_eq!((vertical.radius, vertical.half_length), (15.0, 15.0));
+    assert_eq!(rotation, Rot2::IDENTITY);
+
+    let (horizontal, rotation) =
+        capsule_from_size(Vector::new(60.0, 30.0), CapsuleAxis::Horizontal);
+    assert_eq!((horizontal.radius, horizontal.half_length), (15.0, 15.0));
+    assert_eq!(rotation, Rot2::FRAC_PI_2);
+    let aabb = capsule_collider(Vector::new(60.0, 30.0), CapsuleAxis::Horizontal)
+        .aabb(Vector::ZERO, Rotation::IDENTITY)
+        .size();
+    assert!(aabb.distance(Vector::new(60.0, 30.0)) < 1e-3);
+
+    // Too short along its axis for a straight section, so it's a circle across the short side
+    let (circle, _) = capsule_from_size(Vector::new(60.0, 30.0), CapsuleAxis::Vertical);
+    assert_eq!((circle.radius, circle.half_length), (15.0, 0.0));
+}
+
+#[test]
+fn reset_clears_a_controller_mid_jump() {
+    let mut app = test_app();
+    spawn_floor(&mut app);
+    let controller = spawn_controller(
+        &mut app,
+        (controller_bundle().with_jump_buffer(0.2), AirJumps(1)),
+        Vector::Y * STANDING_HEIGHT,
+    );
+    step(&mut app, 5);
+
+    // A jump, the air jump, and a third press that can only be buffered
+    for _ in 0..2 {
+        send(&mut app, controller, MovementAction::Jump);
+        step(&mut app, 10);
+    }
+    send(&mut app, controller, MovementAction::Jump);
+    app.update();
+    assert_eq!(component::<JumpState>(&app, controller).air_jumps_used, 1);
+    assert!(component::<JumpBuffer>(&app, controller).remaining > 0.0);
+    assert!(component::<AirTime>(&app, controller).0 > 0.0);
+
+    // Without gravity nothing gives the controller velocity again after the reset
+    insert(&mut app, controller, GravityEnabled(false));
+    app.world_mut().send_event(ResetControllersEvent);
+    app.update();
+
+    assert_eq!(velocity(&app, controller), Vector::ZERO);
+    assert_eq!(component::<JumpState>(&app, controller).air_jumps_used, 0);
+    assert_eq!(component::<JumpBuffer>(&app, controller).remaining, 0.0);
+    assert_eq!(component::<CoyoteTime>(&app, controller).remaining, 0.0);
+    assert!(component::<AirTime>(&app, controller).0 <= 1.0 / 60.0 + 1e-6);
+    assert!(!app.world().entity(controller).contains::<JumpRising>());
+    assert_ne!(state(&app, controller), CharacterControllerState::Jumping);
+}
+
+#[test]
+fn curve_scale_measures_progress_across_up_and_survives_no_top_speed() {
+    let curve = MovementCurve::EaseOut;
+    // Half of top speed along each up's right, whatever the speed along up
+    for (up, velocity) in [
+        (Dir2::Y, Vector::new(50.0, -300.0)),
+        (Dir2::NEG_Y, Vector::new(-50.0, 300.0)),
+        (Dir2::X, Vector::new(-300.0, -50.0)),
+        (Dir2::NEG_X, Vector::new(300.0, 50.0)),
+    ] {
+        let scale = curve_scale(&curve, velocity, up, 1.0, 100.0);
+        assert!(
+            (scale - 1.0).abs() < 1e-5,
+            "scaled by {scale} with {up:?} up"
+        );
+    }
+    // Moving against the walk direction counts as no progress
+    assert_eq!(
+        curve_scale(&curve, Vector::new(50.0, 0.0), Dir2::Y, -1.0, 100.0),
+        1.5
+    );
+
+    for top_speed in [0.0, Scalar::INFINITY, Scalar::NAN] {
+        assert_eq!(
+            curve_scale(&curve, Vector::new(50.0, 0.0), Dir2::Y, 1.0, top_speed),
+            1.0
+        );
+    }
+}
+
+#[test]
+fn curves_with_no_damping_left_keep_the_velocity_finite() {
+    let mut app = test_app();
+    spawn_floor(&mut app);
+    let controller = spawn_controller(
+        &mut app,
+        (controller_bundle(), MovementCurve::EaseOut),
+        Vector::Y * STANDING_HEIGHT,
+    );
+    step(&mut app, 5);
+    insert(&mut app, controller, MovementDamping(0.0));
+
+    for _ in 0..10 {
+        send(&mut app, controller, MovementAction::Walk(1.0));
+        app.update();
+        assert!(velocity(&app, controller).is_finite());
+    }
+    assert!(position(&app, controller).is_finite());
+}
+
+#[test]
+fn ease_out_gets_going_faster_than_linear() {
+    let mut app = test_app();
+    spawn_floor(&mut app);
+    let [linear, ease_out] = [
+        (MovementCurve::Linear, -200.0),
+        (MovementCurve::EaseOut, 200.0),
+    ]
+    .map(|(curve, x)| {
+        spawn_controller(
+            &mut app,
+            (controller_bundle(), curve),
+            Vector::new(x, STANDING_HEIGHT),
+        )
+    });
+    step(&mut app, 5);
+
+    let mut profiles = [Vec::new(), Vec::new()];
+    for _ in 0..10 {
+        for controller in [linear, ease_out] {
+            send(&mut app, controller, MovementAction::Walk(1.0));
+        }
+        app.update();
+        for (profile, controller) in profiles.iter_mut().zip([linear, ease_out]) {
+            profile.push(velocity(&app, controller).x);
+        }
+    }
+
+    assert!(profiles[1][0] > profiles[0][0]);
+    assert_ne!(profiles[0], profiles[1]);
+}
+
+#[test]
+fn movement_curves_work_towards_the_top_speed_on_slippery_ground() {
+    let mut app = test_app();
+    let ice = spawn_floor(&mut app);
+    insert(&mut app, ice, SurfaceFriction(0.25));
+    let controller = spawn_controller(
+        &mut app,
+        (controller_bundle(), MovementCurve::Exponential),
+        Vector::new(-1800.0, STANDING_HEIGHT),
+    );
+    step(&mut app, 5);
+
+    for _ in 0..60 {
+        send(&mut app, controller, MovementAction::Walk(1.0));
+        app.update();
+    }
+
+    // The exponential curve stops accelerating at top speed, which the ice raises well past the
+    // top speed on ordinary ground
+    let ground_top_speed = top_walk_speed(
+        component::<MovementAcceleration>(&app, controller).0,
+        component::<MovementDamping>(&app, controller).0,
+    );
+    let speed = velocity(&app, controller).x;
+    assert!(speed > 2.0 * ground_top_speed, "walked at {speed}");
+}
+#[test]
+fn clipping_a_platform_corner_nudges_the_jump_past_it() {
+    let mut app = test_app();
+    spawn_floor(&mut app);
+    // A platform whose left edge overlaps the right 4 units of the controller
+    let edge = CONTROLLER_SIZE.x / 2.0 - 4.0;
+    spawn_box(
+        &mut app,
+        Vector::new(edge + 100.0, 160.0),
+        Vector::new(200.0, 20.0),
+    );
+    let controller = spawn_controller(
+        &mut app,
+        (
+            controller_bundle(),
+            CornerCorrectionWidth(8.0),
+            MaxJumpHeight(200.0),
+        ),
+        Vector::Y * STANDING_HEIGHT,
+    );
+    step(&mut app, 5);
+
+    send(&mut app, controller, MovementAction::Jump);
+    let mut highest = position(&app, controller).y;
+    for _ in 0..40 {
+        app.update();
+        highest = highest.max(position(&app, controller).y);
+    }
+
+    assert!(highest > 170.0, "{highest}");
+    assert!(position(&app, controller).x < -4.0);
+}
+
+#[test]
+fn corner_nudges_run_perpendicular_to_up() {
+    let mut app = test_app();
+    // Rising along +x, a wall ahead whose edge overlaps 4 units of the circle's -y side
+    spawn_box(
+        &mut app,
+        Vector::new(35.0, -111.0),
+        Vector::new(20.0, 200.0),
+    );
+    step(&mut app, 2);
+
+    let config = CollideAndSlideConfig {
+        up: Dir2::X,
+        ..default()
+    };
+    let nudge = app
+        .world_mut()
+        .run_system_once(move |spatial_query: Res<SpatialQueryPipeline>| {
+            let collider = Collider::circle(15.0);
+            let motion = Vector::X * 20.0;
+            let ceiling = spatial_query
+                .cast_shape(
+                    &collider,
+                    Vector::ZERO,
+                    0.0,
+                    Dir2::X,
+                    &ShapeCastConfig::from_max_distance(motion.length()),
+                    &config.filter,
+                )
+                .unwrap();
+            corner_nudge(
+                &spatial_query,
+                &collider,
+                (Vector::ZERO, motion),
+                &ceiling,
+                8.0,
+                &config,
+                &|_, _| false,
+            )
+        })
+        .unwrap()
+        .unwrap();
+
+    assert_eq!(nudge.x, 0.0);
+    assert!(nudge.y > 4.0 && nudge.y <= 8.0);
+}
+
+#[test]
+fn idle_and_moving_split_at_the_epsilon() {
+    let below = Vector::X * (IDLE_SPEED_EPSILON - 0.01);
+    let above = Vector::Y * (IDLE_SPEED_EPSILON + 0.01);
+
+    assert!(!is_moving(below, IDLE_SPEED_EPSILON));
+    assert!(is_moving(above, IDLE_SPEED_EPSILON));
+    assert!(is_idle(below, true, IDLE_SPEED_EPSILON));
+    assert!(!is_idle(above, true, IDLE_SPEED_EPSILON));
+    // Hanging still in the air isn't idle
+    assert!(!is_idle(Vector::ZERO, false, IDLE_SPEED_EPSILON));
+}
+
+/// Holds jump on `controller` for `steps` steps and returns its speed along y after each one.
+fn hold_jump(app: &mut App, controller: Entity, steps: usize) -> Vec<Scalar> {
+    (0..steps)
+        .map(|_| {
+            send(app, controller, MovementAction::JumpHeld);
+            app.update();
+            velocity(app, controller).y
+        })
+        .collect()
+}
+
+#[test]
+fn jump_thrust_pushes_up_until_the_fuel_runs_out() {
+    let mut app = test_app();
+    // A quarter of a second of thrust at three times gravity
+    let controller = spawn_controller(
+        &mut app,
+        (controller_bundle(), JumpThrust::new(3.0 * GRAVITY, 0.25)),
+        Vector::Y * 500.0,
+    );
+    app.update();
+
+    let speeds = hold_jump(&mut app, controller, 30);
+
+    assert!(
+        speeds[..14].windows(2).all(|pair| pair[1] > pair[0]),
+        "{speeds:?}"
+    );
+    assert!(
+        speeds[17..].windows(2).all(|pair| pair[1] < pair[0]),
+        "{speeds:?}"
+    );
+    assert_eq!(component::<JumpThrust>(&app, controller).remaining(), 0.0);
+}
+
+#[test]
+fn respawns_and_resets_refill_jump_thrust() {
+    let mut app = test_app();
+    let controller = spawn_controller(
+        &mut app,
+        (controller_bundle(), JumpThrust::new(3.0 * GRAVITY, 0.25)),
+        Vector::Y * 500.0,
+    );
+    app.update();
+
+    hold_jump(&mut app, controller, 20);
+    assert_eq!(component::<JumpThrust>(&app, controller).remaining(), 0.0);
+    app.world_mut().send_event(RespawnEvent {
+        entity: controller,
+        position: Vector::Y * 500.0,
+    });
+    app.update();
+    assert_eq!(component::<JumpThrust>(&app, controller).remaining(), 0.25);
+
+    hold_jump(&mut app, controller, 20);
+    assert_eq!(component::<JumpThrust>(&app, controller).remaining(), 0.0);
+    app.world_mut().send_event(ResetControllersEvent);
+    app.update();
+    assert_eq!(component::<JumpThrust>(&app, controller).remaining(), 0.25);
+}
+
+#[test]
+fn changing_the_collider_resizes_the_ground_caster_and_keeps_the_feet_in_place() {
+    let mut app = test_app();
+    spawn_floor(&mut app);
+    let controller = spawn_controller(&mut app, controller_bundle(), Vector::Y * STANDING_HEIGHT);
+    step(&mut app, 5);
+    let feet = position(&app, controller).y - CONTROLLER_SIZE.y / 2.0;
+
+    let crouched = Vector::new(CONTROLLER_SIZE.x, 40.0);
+    app.world_mut().send_event(ChangeColliderEvent {
+        entity: controller,
+        collider: capsule_collider(crouched, CapsuleAxis::Vertical),
+    });
+    app.update();
+
+    let size = |collider: &Collider| collider.aabb(Vector::ZERO, Rotation::IDENTITY).size();
+    let scale = component::<GroundCasterScale>(&app, controller).0;
+    let caster = size(&component::<ShapeCaster>(&app, controller).shape);
+    assert!(caster.distance(crouched * scale) < 1e-3, "{caster}");
+    assert!(size(component::<Collider>(&app, controller)).distance(crouched) < 1e-3);
+
+    step(&mut app, 5);
+    let crouched_feet = position(&app, controller).y - crouched.y / 2.0;
+    assert!((crouched_feet - feet).abs() < 1.0);
+    assert!(is_grounded(&app, controller));
+}
+
+#[test]
+fn jumps_off_a_slope_lean_towards_its_normal() {
+    let mut app = test_app();
+    // Rising to the right, so its normal leans left
+    spawn_slope(&mut app, 45.0, 1.0);
+    let controller = spawn_controller(
+        &mut app,
+        (controller_bundle(), SlopeJump(0.5)),
+        Vector::new(150.0, 250.0),
+    );
+    let landed = (0..120).any(|_| {
+        app.update();
+        is_grounded(&app, controller)
+    });
+    assert!(landed);
+    let before = velocity(&app, controller);
+
+    send(&mut app, controller, MovementAction::Jump);
+    app.update();
+
+    let jump = velocity(&app, controller) - before;
+    assert!(jump.y > 100.0, "{jump}");
+    assert!(jump.x < -50.0, "{jump}");
+}
+
+#[test]
+fn slides_only_consider_colliders_near_their_swept_bounds() {
+    let mut app = test_app();
+    let wall = spawn_box(&mut app, Vector::new(60.0, 0.0), Vector::new(20.0, 200.0));
+    for i in 0..50 {
+        spawn_box(
+            &mut app,
+            Vector::new(-1000.0 - 50.0 * i as Scalar, 500.0),
+            Vector::splat(20.0),
+        );
+    }
+    // Above the swept bounds by more than one substep's worth of pushes, but fewer than four
+    let above = spawn_box(&mut app, Vector::new(50.0, 25.0), Vector::splat(20.0));
+    // The spatial query pipeline only picks up the new colliders on the first physics step
+    app.update();
+
+    let slide = |substeps: usize| {
+        move |spatial_query: Res<SpatialQueryPipeline>| {
+            let config = CollideAndSlideConfig {
+                substeps,
+                ..default()
+            };
+            let collider = Collider::circle(10.0);
+            let motion = Vector::X * 100.0;
+            let candidates =
+                slide_candidates(&spatial_query, &collider, Vector::ZERO, motion, &config);
+            let result = collide_and_slide(
+                &spatial_query,
+                &collider,
+                Vector::ZERO,
+                motion,
+                &config,
+                &|_, _| false,
+            );
+            (candidates, result.hits.first().map(|hit| hit.entity))
+        }
+    };
+
+    let (candidates, hit) = app.world_mut().run_system_once(slide(1)).unwrap();
+    assert_eq!(candidates, EntityHashSet::from_iter([wall]));
+    assert_eq!(hit, Some(wall));
+
+    let (candidates, hit) = app.world_mut().run_system_once(slide(4)).unwrap();
+    assert_eq!(candidates, EntityHashSet::from_iter([wall, above]));
+    assert_eq!(hit, Some(wall));
+}
+#[test]
+fn only_ground_layers_ground_the_controller() {
+    const TERRAIN: LayerMask = LayerMask(0b01);
+    const HAZARDS: LayerMask = LayerMask(0b10);
+
+    let mut app = test_app();
+    let hazard = spawn_box(
+        &mut app,
+        Vector::new(-300.0, -10.0),
+        Vector::new(200.0, 20.0),
+    );
+    insert(
+        &mut app,
+        hazard,
+        CollisionLayers::new(HAZARDS, LayerMask::ALL),
+    );
+    let terrain = spawn_box(
+        &mut app,
+        Vector::new(300.0, -10.0),
+        Vector::new(200.0, 20.0),
+    );
+    insert(
+        &mut app,
+        terrain,
+        CollisionLayers::new(TERRAIN, LayerMask::ALL),
+    );
+    let controller = spawn_controller(
+        &mut app,
+        (controller_bundle(), GroundLayers(TERRAIN)),
+        Vector::new(-300.0, 100.0),
+    );
+
+    step(&mut app, 60);
+
+    // The hazard still holds the controller up, it just isn't ground
+    assert!((position(&app, controller).y - STANDING_HEIGHT).abs() < 1.0);
+    assert!(!is_grounded(&app, controller));
+
+    app.world_mut().send_event(RespawnEvent {
+        entity: controller,
+        position: Vector::new(300.0, 100.0),
+    });
+    step(&mut app, 60);
+
+    assert!((position(&app, controller).y - STANDING_HEIGHT).abs() < 1.0);
+    assert!(is_grounded(&app, controller));
+}
+
+#[test]
+fn the_ground_caster_takes_its_scale_from_the_bundle() {
+    let mut app = test_app();
+    let default = spawn_controller(&mut app, controller_bundle(), Vector::ZERO);
+    let custom = spawn_controller(
+        &mut app,
+        controller_bundle().with_ground_caster_scale(0.8),
+        Vector::X * 200.0,
+    );
+
+    let caster = |controller| {
+        component::<ShapeCaster>(&app, controller)
+            .shape
+            .aabb(Vector::ZERO, Rotation::IDENTITY)
+            .size()
+    };
+    assert_eq!(
+        *component::<GroundCasterScale>(&app, custom),
+        GroundCasterScale(0.8)
+    );
+    assert!(caster(custom).distance(CONTROLLER_SIZE * 0.8) < 1e-3);
+    assert!(caster(default).distance(CONTROLLER_SIZE * 0.99) < 1e-3);
+}
+
+#[test]
+fn capsule_bundles_get_an_upright_capsule_of_the_given_size() {
+    let bundle = CharacterControllerBundle::capsule(30.0, 80.0, Vector::NEG_Y * GRAVITY);
+    let capsule = bundle.collider.shape().as_capsule().unwrap();
+    assert_eq!((capsule.radius, capsule.half_height()), (15.0, 25.0));
+    let size = bundle
+        .collider
+        .aabb(Vector::ZERO, Rotation::IDENTITY)
+        .size();
+    assert!(size.distance(Vector::new(30.0, 80.0)) < 1e-3);
+
+    // Shorter than it is wide, so it's a circle across the height
+    let bundle = CharacterControllerBundle::capsule(60.0, 30.0, Vector::NEG_Y * GRAVITY);
+    let circle = bundle.collider.shape().as_capsule().unwrap();
+    assert_eq!((circle.radius, circle.half_height()), (15.0, 0.0));
+}
@@ -0,0 +1,5 @@
+// Bevy queries nest component tuples, which this lint flags all the time
+#![allow(clippy::type_complexity)]
+
+pub mod camera;
+pub mod character_controller;
@@ -3,18 +3,24 @@ use bevy::{
     input::mouse::{MouseScrollUnit, MouseWheel},
     prelude::*,
 };
+use platform_controller::{
+    camera::{FollowCameraPlugin, SpringFollow},
+    character_controller::{
+        CapsuleAxis, CharacterController, CharacterControllerBundle, CharacterControllerPlugin,
+        RespawnEvent, capsule_collider, capsule_from_size,
+    },
+};
 
 const CONTROLLER_COLOR: Color = Color::srgb(0.0, 0.0, 0.0);
 const OBSTACLE_COLOR: Color = Color::srgb(0.2, 0.7, 0.9);
 
 const CONTROLLER_SIZE: Vector = Vector::new(30.0, 60.0); // Total width and height of the controller's capsule collider
 const CONTROLLER_INITIAL_POSITION: Vector = Vector::new(0.0, 100.0);
-// const CONTROLLER_SKIN_WIDTH: f32 = 4.0;
+const CAMERA_STIFFNESS: f32 = 50.0;
+// Trackpads scroll in pixels, this converts them to roughly one mouse wheel notch
+const PIXELS_PER_SCROLL_LINE: f32 = 20.0;
 
-const HORIZONTAL_PLAYER_SPEED: f32 = 100.0;
-const GRAVITY: f32 = 100.0;
-const JUMP_SPEED: f32 = 50.0;
-// const MINIMUM_MOVEMENT_DISTANCE: f32 = 0.0001;
+const GRAVITY: f32 = 1000.0;
 
 fn platform_from_position(position: Vector, size: Vector, rotation: Scalar) -> impl Bundle {
     (
@@ -30,8 +36,16 @@ fn platform_from_position(position: Vector, size: Vector, rotation: Scalar) -> i
     )
 }
 
-fn capsule_from_size(size: Vector) -> Capsule2d {
-    Capsule2d::new(size.x / 2.0, size.y - size.x)
+/// Spawns one static platform per `(position, size, rotation)` entry.
+fn spawn_platforms(commands: &mut Commands, platforms: &[(Vector, Vector, Scalar)]) {
+    for &(position, size, rotation) in platforms {
+        commands.spawn(platform_from_position(position, size, rotation));
+    }
+}
+
+fn capsule_mesh(size: Vector, axis: CapsuleAxis) -> Mesh {
+    let (capsule, rotation) = capsule_from_size(size, axis);
+    Mesh::from(capsule).rotated_by(Quat::from_rotation_z(rotation.as_radians()))
 }
 
 fn setup(
@@ -39,34 +53,52 @@ fn setup(
     mut meshes: ResMut<Assets<Mesh>>,
     mut materials: ResMut<Assets<ColorMaterial>>,
 ) {
+    let controller = commands
+        .spawn((
+            Mesh2d(meshes.add(capsule_mesh(CONTROLLER_SIZE, CapsuleAxis::Vertical))),
+            MeshMaterial2d(materials.add(CONTROLLER_COLOR)),
+            CharacterControllerBundle::new(
+                capsule_collider(CONTROLLER_SIZE, CapsuleAxis::Vertical),
+                Vector::NEG_Y * GRAVITY,
+            ),
+            Transform::from_translation(CONTROLLER_INITIAL_POSITION.extend(0.0)),
+        ))
+        .id();
+
+    // The camera isn't parented to the controller so it can ease towards it
     commands.spawn((
-        Mesh2d(meshes.add(capsule_from_size(CONTROLLER_SIZE))),
-        MeshMaterial2d(materials.add(CONTROLLER_COLOR)),
-        ControllerBundle::new(CONTROLLER_SIZE, CONTROLLER_INITIAL_POSITION),
-        // This allows the camera to follow the player's position
-        children![(
-            Camera2d,
-            Projection::Orthographic(OrthographicProjection::default_2d())
-        )],
+        Camera,
+        Camera2d,
+        Projection::Orthographic(OrthographicProjection::default_2d()),
+        SpringFollow::critically_damped(controller, CAMERA_STIFFNESS),
     ));
 
-    commands.spawn(platform_from_position(
-        Vector::new(0.0, 0.0),
-        Vector::new(100.0, 10.0),
-        0.0,
-    ));
+    spawn_platforms(
+        &mut commands,
+        &[
+            (Vector::new(0.0, 0.0), Vector::new(100.0, 10.0), 0.0),
+            (Vector::new(150.0, 60.0), Vector::new(100.0, 10.0), 0.0),
+            (Vector::new(-160.0, 30.0), Vector::new(120.0, 10.0), 20.0),
+        ],
+    );
+}
 
-    commands.spawn(platform_from_position(
-        Vector::new(0.0, 0.0),
-        Vector::new(100.0, 10.0),
-        0.0,
-    ));
+/// Puts every controller back at the start when R is pressed.
+fn respawn_on_r(
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    controllers: Query<Entity, With<CharacterController>>,
+    mut respawn_events: EventWriter<RespawnEvent>,
+) {
+    if !keyboard_input.just_pressed(KeyCode::KeyR) {
+        return;
+    }
 
-    commands.spawn(platform_from_position(
-        Vector::new(0.0, 0.0),
-        Vector::new(100.0, 10.0),
-        0.0,
-    ));
+    for entity in &controllers {
+        respawn_events.write(RespawnEvent {
+            entity,
+            position: CONTROLLER_INITIAL_POSITION,
+        });
+    }
 }
 
 fn close_on_esc(mut exit: ResMut<Events<AppExit>>, keyboard_input: Res<ButtonInput<KeyCode>>) {
@@ -75,31 +107,49 @@ fn close_on_esc(mut exit: ResMut<Events<AppExit>>, keyboard_input: Res<ButtonInp
     }
 }
 
+/// Marks the platformer's camera, so zooming doesn't affect any other camera in the app.
 #[derive(Component)]
 struct Camera;
 
+#[derive(Resource)]
+struct CameraZoom {
+    sensitivity: f32,
+    min_scale: f32,
+    max_scale: f32,
+}
+
+impl Default for CameraZoom {
+    fn default() -> Self {
+        CameraZoom {
+            sensitivity: 0.1,
+            min_scale: 0.1,
+            max_scale: 10.0,
+        }
+    }
+}
+
 fn zoom_camera(
+    zoom: Res<CameraZoom>,
     mut mouse_scroll: EventReader<MouseWheel>,
-    mut camera: Query<&mut Projection, With<Camera2d>>,
+    mut camera: Query<&mut Projection, With<Camera>>,
 ) {
     let mut camera_projection = match camera.single_mut() {
         Ok(projection) => projection,
         Err(_) => return,
     };
 
-    match &mut *camera_projection {
-        Projection::Orthographic(projection) => {
-            for scroll in mouse_scroll.read() {
-                match scroll.unit {
-                    MouseScrollUnit::Line => {
-                        let scale = projection.scale;
-                        projection.scale = (scale - scroll.y * 0.1).max(0.1);
-                    }
-                    _ => (),
-                }
-            }
-        }
-        _ => (),
+    let Projection::Orthographic(projection) = &mut *camera_projection else {
+        return;
+    };
+
+    for scroll in mouse_scroll.read() {
+        let lines = match scroll.unit {
+            MouseScrollUnit::Line => scroll.y,
+            MouseScrollUnit::Pixel => scroll.y / PIXELS_PER_SCROLL_LINE,
+        };
+
+        let scale = projection.scale - lines * zoom.sensitivity;
+        projection.scale = scale.clamp(zoom.min_scale, zoom.max_scale);
     }
 }
 
@@ -109,162 +159,115 @@ fn main() {
             DefaultPlugins,
             PhysicsPlugins::default().with_length_unit(20.0),
             PhysicsDebugPlugin::default(),
-            ControllerPlugin,
+            CharacterControllerPlugin::new(60.0),
+            FollowCameraPlugin,
         ))
+        .init_resource::<CameraZoom>()
         .add_systems(Startup, setup)
-        .add_systems(Update, (close_on_esc, zoom_camera))
-        .insert_resource(Time::<Fixed>::from_hz(60.0))
+        .add_systems(Update, (close_on_esc, respawn_on_r, zoom_camera))
         .run();
 }
 
-#[derive(Component)]
-struct Controller;
-
-#[derive(Bundle)]
-struct ControllerBundle {
-    transform: Transform,
-    velocity: LinearVelocity,
-    collider: Collider,
-    rigidbody: RigidBody,
-}
+#[cfg(test)]
+mod tests {
+    use bevy::ecs::system::RunSystemOnce;
 
-impl ControllerBundle {
-    fn new(size: Vector, starting_position: Vector) -> impl Bundle {
-        (
-            ControllerBundle {
-                transform: Transform::from_translation(starting_position.extend(0.0)),
-                velocity: LinearVelocity(Vector::ZERO),
-                collider: capsule_from_size(size).into(),
-                rigidbody: RigidBody::Kinematic,
-            },
-            Controller,
-        )
+    use super::*;
+
+    fn test_app() -> App {
+        let mut app = App::new();
+        app.add_plugins(MinimalPlugins)
+            .add_event::<MouseWheel>()
+            .init_resource::<CameraZoom>()
+            .add_systems(Update, zoom_camera);
+        app
     }
-}
 
-#[derive(Event)]
-enum ControllerMovement {
-    HorizontalMovement(f32),
-    SetPosition(Vector),
-    Jump,
-}
+    fn spawn_camera(app: &mut App) -> Entity {
+        app.world_mut()
+            .spawn(Projection::Orthographic(
+                OrthographicProjection::default_2d(),
+            ))
+            .id()
+    }
 
-struct ControllerPlugin;
-
-impl Plugin for ControllerPlugin {
-    fn build(&self, app: &mut App) {
-        app.add_event::<ControllerMovement>()
-            .add_systems(
-                PhysicsSchedule,
-                // TODO: explain why we put `collision_response` in the narrow phase
-                collision_response.in_set(NarrowPhaseSet::Last),
-            )
-            .add_systems(Update, controller_input)
-            .add_systems(FixedUpdate, controller_movement);
+    fn scroll(app: &mut App, unit: MouseScrollUnit, y: f32) {
+        app.world_mut().send_event(MouseWheel {
+            unit,
+            x: 0.0,
+            y,
+            window: Entity::PLACEHOLDER,
+        });
+        app.update();
     }
-}
 
-fn controller_input(
-    keyboard_input: Res<ButtonInput<KeyCode>>,
-    mut controller_movement_events: EventWriter<ControllerMovement>,
-) {
-    let mut horizontal_velocity = 0.0;
-    if keyboard_input.pressed(KeyCode::KeyD) {
-        horizontal_velocity += 1.0;
-    } else if keyboard_input.pressed(KeyCode::KeyA) {
-        horizontal_velocity -= 1.0;
+    fn scale(app: &App, camera: Entity) -> f32 {
+        match app.world().get::<Projection>(camera).unwrap() {
+            Projection::Orthographic(projection) => projection.scale,
+            _ => unreachable!(),
+        }
     }
 
-    use ControllerMovement as Event;
-    controller_movement_events.write(Event::HorizontalMovement(horizontal_velocity));
+    #[test]
+    fn only_the_tagged_camera_zooms() {
+        let mut app = test_app();
+        let tagged = spawn_camera(&mut app);
+        app.world_mut().entity_mut(tagged).insert(Camera);
+        let other = spawn_camera(&mut app);
+
+        scroll(&mut app, MouseScrollUnit::Line, 1.0);
 
-    if keyboard_input.just_pressed(KeyCode::Space) {
-        controller_movement_events.write(Event::Jump);
+        assert!(scale(&app, tagged) < 1.0);
+        assert_eq!(scale(&app, other), 1.0);
     }
 
-    if keyboard_input.just_pressed(KeyCode::KeyR) {
-        controller_movement_events.write(Event::SetPosition(CONTROLLER_INITIAL_POSITION));
+    #[test]
+    fn pixel_scrolls_zoom_too() {
+        let mut app = test_app();
+        let camera = spawn_camera(&mut app);
+        app.world_mut().entity_mut(camera).insert(Camera);
+
+        scroll(
+            &mut app,
+            MouseScrollUnit::Pixel,
+            2.0 * PIXELS_PER_SCROLL_LINE,
+        );
+
+        assert!((scale(&app, camera) - 0.8).abs() < 1e-5);
     }
-}
 
-fn controller_movement(
-    time: Res<Time<Fixed>>,
-    mut controller_movement_events: EventReader<ControllerMovement>,
-    mut controllers: Query<(&mut LinearVelocity, &mut Transform), With<Controller>>,
-) {
-    for event in controller_movement_events.read() {
-        for (mut controller_velocity, mut controller_transform) in &mut controllers {
-            use ControllerMovement as Event;
-            match event {
-                Event::HorizontalMovement(magnitude) => {
-                    controller_velocity.x = magnitude * HORIZONTAL_PLAYER_SPEED
-                }
-                Event::Jump => controller_velocity.y = JUMP_SPEED,
-                Event::SetPosition(position) => {
-                    controller_transform.translation = position.extend(0.0);
-                }
-            }
-
-            controller_velocity.y -= GRAVITY * time.delta_secs();
-        }
+    #[test]
+    fn zooming_out_saturates_at_the_maximum_scale() {
+        let mut app = test_app();
+        let camera = spawn_camera(&mut app);
+        app.world_mut().entity_mut(camera).insert(Camera);
+
+        scroll(&mut app, MouseScrollUnit::Line, -1000.0);
+
+        assert_eq!(scale(&app, camera), CameraZoom::default().max_scale);
     }
-}
 
-// struct CollideAndSlideConfig {
-//     bounces: usize,
-//     rotation: Scalar,
-//     skin_width: Scalar,
-//     max_slope_angle: Scalar,
-//     filter: SpatialQueryFilter,
-// }
-//
-// impl Default for CollideAndSlideConfig {
-//     fn default() -> Self {
-//         CollideAndSlideConfig {
-//             bounces: 10,
-//             rotation: 0.0,
-//             skin_width: 0.015,
-//             max_slope_angle: 60.0,
-//             // this filter collides with everything
-//             filter: SpatialQueryFilter::from_excluded_entities([]),
-//         }
-//     }
-// }
-//
-
-fn collision_response(
-    time: Res<Time<Fixed>>,
-    spatial_query: Res<SpatialQueryPipeline>,
-    mut controllers: Query<(&mut LinearVelocity, &Transform, &Collider, Entity), With<Controller>>,
-) {
-    for (mut velocity, transform, collider, entity) in &mut controllers {
-        let cast_direction = match velocity.y.signum() {
-            1.0 => Dir2::Y,
-            -1.0 => Dir2::NEG_Y,
-            0.0 => continue, // If the controller is still, we don't compute collisions for it
-            _ => {
-                panic!("Velocity should never be NaN");
-            }
-        };
-        let cast_origin = transform.translation.xy();
-        // Excluding the controller entity prevents controllers from colliding with themselves
-        let cast_filter = SpatialQueryFilter::from_excluded_entities([entity]);
-
-        let delta_secs = time.delta_secs();
-        if let Some(hit) = spatial_query.cast_shape(
-            &collider,
-            cast_origin,
-            0.0, // TODO: support rotations
-            cast_direction,
-            &ShapeCastConfig {
-                max_distance: velocity.y.abs() * delta_secs,
-                ..default()
-            },
-            &cast_filter,
-        ) {
-            let snap_to_surface = hit.distance * velocity.y.signum() / delta_secs;
-
-            velocity.y = snap_to_surface;
-        }
+    #[test]
+    fn spawn_platforms_spawns_one_static_body_per_entry() {
+        let mut world = World::new();
+        world
+            .run_system_once(|mut commands: Commands| {
+                spawn_platforms(
+                    &mut commands,
+                    &[
+                        (Vector::new(0.0, 0.0), Vector::new(100.0, 10.0), 0.0),
+                        (Vector::new(150.0, 60.0), Vector::new(80.0, 10.0), 0.0),
+                        (Vector::new(-160.0, 30.0), Vector::new(120.0, 10.0), 20.0),
+                    ],
+                );
+            })
+            .unwrap();
+
+        let mut bodies = world.query::<&RigidBody>();
+        let static_bodies = bodies
+            .iter(&world)
+            .filter(|body| **body == RigidBody::Static)
+            .count();
+        assert_eq!(static_bodies, 3);
     }
 }